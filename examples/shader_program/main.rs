@@ -38,7 +38,7 @@ fn main() {
         });
     }
     canvas.shade::<VertexIn, ShaderUniform, BVec<f32, 6>, VertShader, FragShader>(&vertices,
-        &&ShaderUniform {
+        &ShaderUniform {
             texture,
         },
         BlendMode::Alpha
@@ -12,7 +12,7 @@ impl VertexShader for VertShader {
     type Out = BVec<f32, 6>;
 
     fn shade(attr: &Self::Attribute, _uniform: &Self::Uniform) -> bezier::shading::VertexOut<Self::Out> {
-        let ans = VertexOut::<BVec<f32, 6>> {
+        VertexOut::<BVec<f32, 6>> {
             coord: attr.xy,
             varying: BVec {
                 v: [
@@ -24,7 +24,6 @@ impl VertexShader for VertShader {
                     attr.color.w(),
                 ]
             },
-        };
-        ans
+        }
     }
 }
\ No newline at end of file
@@ -0,0 +1,174 @@
+use num::Zero;
+use rayon::prelude::*;
+
+use crate::linalg::{Vec2, Vec4};
+use crate::types::colortype::{InternalColorType, ColorType};
+use crate::canvas::{BezierCanvas, coverage, xy_to_pixel};
+
+// normalized kernel of a gaussian with standard deviation `sigma` in pixels, truncated at 3 sigma
+pub(super) fn gaussian_kernel(sigma: f32) -> Vec<f32> {
+    let radius = (3.0 * sigma).ceil().max(0.0) as isize;
+    let kernel: Vec<f32> = (-radius..=radius)
+        .map(|i| (-((i * i) as f32) / (2.0 * sigma * sigma)).exp())
+        .collect();
+    let sum: f32 = kernel.iter().sum();
+    kernel.iter().map(|w| w / sum).collect()
+}
+
+fn clamped(src: &[Vec4], i: isize) -> Vec4 {
+    src[i.clamp(0, src.len() as isize - 1) as usize]
+}
+
+pub(super) fn convolve(src: &[Vec4], dst: &mut [Vec4], kernel: &[f32]) {
+    let radius = (kernel.len() / 2) as isize;
+    for (x, pixel) in dst.iter_mut().enumerate() {
+        *pixel = kernel.iter()
+            .enumerate()
+            .fold(Vec4::zero(), |acc, (k, w)| acc + clamped(src, x as isize + k as isize - radius) * *w);
+    }
+}
+
+// moving average with a running sum, so the cost does not depend on the radius
+fn box_filter(src: &[Vec4], dst: &mut [Vec4], radius: usize) {
+    let radius = radius as isize;
+    let scale = 1.0 / (2 * radius + 1) as f32;
+    let mut sum = (-radius..=radius).fold(Vec4::zero(), |acc, i| acc + clamped(src, i));
+    for (x, pixel) in dst.iter_mut().enumerate() {
+        let x = x as isize;
+        if x > 0 {
+            sum = sum + clamped(src, x + radius) - clamped(src, x - radius - 1);
+        }
+        *pixel = sum * scale;
+    }
+}
+
+pub(super) fn transpose(buffer: &[Vec4], width: usize, height: usize) -> Vec<Vec4> {
+    (0..width * height).into_par_iter()
+        .map(|i| buffer[(i % height) * width + i / height])
+        .collect()
+}
+
+// apply a 1D filter to every row, and then to every column through a transpose; edges are clamped
+pub(super) fn separable<F: Fn(&[Vec4], &mut [Vec4]) + Sync>(buffer: &[Vec4], width: usize, height: usize, filter: F) -> Vec<Vec4> {
+    if width == 0 || height == 0 {
+        return Vec::new();
+    }
+    let mut rows = vec![Vec4::zero(); width * height];
+    rows.par_chunks_mut(width)
+        .zip(buffer.par_chunks(width))
+        .for_each(|(dst, src)| filter(src, dst));
+    let columns = transpose(&rows, width, height);
+    columns.par_chunks(height)
+        .zip(rows.par_chunks_mut(height))
+        .for_each(|(src, dst)| filter(src, dst));
+    transpose(&rows, height, width)
+}
+
+impl <InternalType: InternalColorType, ExternalType: ColorType<InternalType>> BezierCanvas<InternalType, ExternalType> {
+    // gaussian blur of the whole canvas, `sigma` in pixels
+    pub fn gaussian_blur(&mut self, sigma: f32) {
+        self.gaussian_blur_rect(&Vec2::new(0.0, 0.0), &Vec2::new(1.0, 1.0), sigma);
+    }
+
+    // gaussian blur of the pixels in a rect, pixels outside the rect are not read
+    pub fn gaussian_blur_rect(&mut self, pos: &Vec2, size: &Vec2, sigma: f32) {
+        if sigma <= 0.0 {
+            return;
+        }
+        let kernel = gaussian_kernel(sigma);
+        self.filter_rect(pos, size, |buffer, width, height| {
+            separable(buffer, width, height, |src, dst| convolve(src, dst, &kernel))
+        });
+    }
+
+    /*
+        Box blur of the whole canvas with a window of 2 * radius + 1 pixels.
+
+        Repeated passes approach a gaussian blur (3 passes are usually close enough) at a cost independent of the radius.
+     */
+    pub fn box_blur(&mut self, radius: usize, passes: usize) {
+        self.box_blur_rect(&Vec2::new(0.0, 0.0), &Vec2::new(1.0, 1.0), radius, passes);
+    }
+
+    pub fn box_blur_rect(&mut self, pos: &Vec2, size: &Vec2, radius: usize, passes: usize) {
+        self.filter_rect(pos, size, |buffer, width, height| {
+            (0..passes).fold(buffer.to_vec(), |buffer, _| {
+                separable(&buffer, width, height, |src, dst| box_filter(src, dst, radius))
+            })
+        });
+    }
+
+    /*
+        Composite a blurred shadow of the canvas content underneath it.
+
+        The shadow has the alpha of the content, offset by (dx, dy) pixels and blurred by `sigma` pixels, in the given color.
+        Meant for canvases with alpha, e.g. on a layer holding the shape that casts the shadow.
+     */
+    pub fn drop_shadow(&mut self, dx: isize, dy: isize, sigma: f32, color: &ExternalType) {
+        let space = self.working_space;
+        let (width, height) = (self.width, self.height);
+        let shadow_color = space.to_premul_vec4(color);
+        let pixels = &self.pixels;
+        let mut shadow: Vec<Vec4> = (0..width * height).into_par_iter()
+            .map(|i| {
+                let x = (i % width) as isize - dx;
+                let y = (i / width) as isize - dy;
+                if x < 0 || y < 0 || x >= width as isize || y >= height as isize {
+                    return Vec4::zero();
+                }
+                shadow_color * space.to_vec4(&ExternalType::from_value(pixels[y as usize * width + x as usize])).w()
+            })
+            .collect();
+        if sigma > 0.0 {
+            let kernel = gaussian_kernel(sigma);
+            shadow = separable(&shadow, width, height, |src, dst| convolve(src, dst, &kernel));
+        }
+
+        let mask = self.mask.as_deref();
+        self.pixels.par_iter_mut()
+            .zip(shadow.par_iter())
+            .enumerate()
+            .for_each(|(i, (pixel, shadow))| {
+                let fg = space.to_premul_vec4(&ExternalType::from_value(*pixel));
+                let composed = space.from_premul_vec4::<InternalType, ExternalType>(fg + *shadow * (1.0 - fg.w())).to_value();
+                *pixel = Self::mix_coverage(*pixel, composed, space, coverage(mask, i));
+            });
+    }
+
+    // replace the pixels in a rect by a filter of them, premultiplied in the working space
+    fn filter_rect<F: FnOnce(&[Vec4], usize, usize) -> Vec<Vec4>>(&mut self, pos: &Vec2, size: &Vec2, filter: F) {
+        if self.width == 0 || self.height == 0 {
+            return;
+        }
+        let x_0 = xy_to_pixel(pos.x().clamp(0.0, 1.0), self.width);
+        let x_1 = xy_to_pixel((pos.x() + size.x()).clamp(0.0, 1.0), self.width).min(self.width - 1);
+        let y_0 = xy_to_pixel(pos.y().clamp(0.0, 1.0), self.height);
+        let y_1 = xy_to_pixel((pos.y() + size.y()).clamp(0.0, 1.0), self.height).min(self.height - 1);
+        if x_0 > x_1 || y_0 > y_1 {
+            return;
+        }
+        let (width, height) = (x_1 + 1 - x_0, y_1 + 1 - y_0);
+        let space = self.working_space;
+        let region: Vec<Vec4> = self.pixels.par_chunks(self.width)
+            .skip(y_0)
+            .take(height)
+            .flat_map(|row| row[x_0..=x_1].par_iter().map(|pixel| space.to_premul_vec4(&ExternalType::from_value(*pixel))))
+            .collect();
+        let filtered = filter(&region, width, height);
+
+        let mask = self.mask.as_deref();
+        let stride = self.width;
+        self.pixels.par_chunks_mut(self.width)
+            .skip(y_0)
+            .take(height)
+            .zip(filtered.par_chunks(width))
+            .enumerate()
+            .for_each(|(i, (row, filtered))| {
+                let y = i + y_0;
+                for (j, (pixel, color)) in row[x_0..=x_1].iter_mut().zip(filtered).enumerate() {
+                    let blurred = space.from_premul_vec4::<InternalType, ExternalType>(*color).to_value();
+                    *pixel = Self::mix_coverage(*pixel, blurred, space, coverage(mask, y * stride + j + x_0));
+                }
+            });
+    }
+}
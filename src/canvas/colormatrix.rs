@@ -0,0 +1,42 @@
+use rayon::prelude::*;
+
+use crate::linalg::Vec4;
+use crate::types::{
+    colortype::{InternalColorType, ColorType},
+    colormatrix::{ColorMatrix, Transfer}
+};
+use crate::canvas::{BezierCanvas, coverage};
+
+impl <InternalType: InternalColorType, ExternalType: ColorType<InternalType>> BezierCanvas<InternalType, ExternalType> {
+    // map every pixel through a color matrix, on straight colors in the working space
+    pub fn color_matrix(&mut self, matrix: &ColorMatrix) {
+        self.map_colors(|color| matrix.apply(color));
+    }
+
+    // map the r, g, b and a channels of every pixel through their transfer functions
+    pub fn component_transfer(&mut self, transfers: &[Transfer; 4]) {
+        self.map_colors(|color| {
+            let mut ans = color;
+            for (c, transfer) in ans.v.iter_mut().zip(transfers) {
+                *c = transfer.apply(*c);
+            }
+            ans
+        });
+    }
+
+    fn map_colors<F: Fn(Vec4) -> Vec4 + Sync>(&mut self, f: F) {
+        let space = self.working_space;
+        let mask = self.mask.as_deref();
+        self.pixels.par_iter_mut()
+            .enumerate()
+            .for_each(|(i, pixel)| {
+                let mut color = f(space.to_vec4(&ExternalType::from_value(*pixel)));
+                for c in color.v.iter_mut().take(3) {
+                    *c = c.max(0.0);
+                }
+                color.v[3] = color.w().clamp(0.0, 1.0);
+                let mapped = space.from_vec4::<InternalType, ExternalType>(color).to_value();
+                *pixel = Self::mix_coverage(*pixel, mapped, space, coverage(mask, i));
+            });
+    }
+}
@@ -0,0 +1,111 @@
+use num::Zero;
+use rayon::prelude::*;
+
+use crate::linalg::Vec4;
+use crate::convert::PNGCompatible;
+use crate::types::{
+    colortype::{InternalColorType, ColorType},
+    compare::Comparison,
+    conversion::Luma
+};
+use crate::canvas::BezierCanvas;
+use crate::canvas::blur::{gaussian_kernel, separable, convolve};
+
+// stabilizing constants of SSIM for a dynamic range of 1
+const SSIM_C1: f32 = 0.01 * 0.01;
+const SSIM_C2: f32 = 0.03 * 0.03;
+
+/*
+    Mean SSIM (Wang et al., 2004) with an 11x11 gaussian window of sigma 1.5,
+    the local means, variances and covariance are gaussian blurs of x, y, x^2, y^2 and xy.
+ */
+fn ssim(x: &[f32], y: &[f32], width: usize, height: usize) -> f32 {
+    // 3 sigma on either side
+    let kernel = gaussian_kernel(1.5);
+    let blur = |buffer: Vec<Vec4>| separable(&buffer, width, height, |src, dst| convolve(src, dst, &kernel));
+    let moments = blur(x.par_iter().zip(y.par_iter()).map(|(x, y)| Vec4::new(*x, *y, x * x, y * y)).collect());
+    let cross = blur(x.par_iter().zip(y.par_iter()).map(|(x, y)| Vec4::new(x * y, 0.0, 0.0, 0.0)).collect());
+    let total: f32 = moments.par_iter()
+        .zip(cross.par_iter())
+        .map(|(m, c)| {
+            let (mu_x, mu_y) = (m.x(), m.y());
+            let var_x = m.z() - mu_x * mu_x;
+            let var_y = m.w() - mu_y * mu_y;
+            let cov = c.x() - mu_x * mu_y;
+            (2.0 * mu_x * mu_y + SSIM_C1) * (2.0 * cov + SSIM_C2)
+                / ((mu_x * mu_x + mu_y * mu_y + SSIM_C1) * (var_x + var_y + SSIM_C2))
+        })
+        .sum();
+    total / (width * height) as f32
+}
+
+impl <InternalType: InternalColorType, ExternalType: ColorType<InternalType>> BezierCanvas<InternalType, ExternalType> {
+    pub fn compare(&self, other: &Self) -> Comparison {
+        assert!(self.width == other.width && self.height == other.height, "canvases of different sizes");
+        let errors: Vec<Vec4> = self.pixels.par_iter()
+            .zip(other.pixels.par_iter())
+            .map(|(a, b)| ExternalType::from_value(*a).to_vec4() - ExternalType::from_value(*b).to_vec4())
+            .collect();
+        let count = (errors.len() * 4).max(1) as f32;
+        let max_error = errors.par_iter()
+            .map(|e| e.v.iter().fold(0f32, |acc, c| acc.max(c.abs())))
+            .reduce(|| 0.0, f32::max);
+        let mean_error = errors.par_iter().map(|e| e.v.iter().map(|c| c.abs()).sum::<f32>()).sum::<f32>() / count;
+        let mse = errors.par_iter().map(|e| e.star(e).v.iter().sum::<f32>()).sum::<f32>() / count;
+        let psnr = if mse > 0.0 { -10.0 * mse.log10() } else { f32::INFINITY };
+
+        let luma = |pixels: &[InternalType]| -> Vec<f32> {
+            pixels.par_iter().map(|p| Luma::Rec709.luma(ExternalType::from_value(*p).to_vec4())).collect()
+        };
+        let ssim = if self.pixels.is_empty() { 1.0 } else { ssim(&luma(&self.pixels), &luma(&other.pixels), self.width, self.height) };
+        Comparison { max_error, mean_error, psnr, ssim }
+    }
+
+    // absolute difference of every channel, opaque, with differences of alpha added to the color channels
+    pub fn diff(&self, other: &Self) -> Self {
+        assert!(self.width == other.width && self.height == other.height, "canvases of different sizes");
+        let mut canvas = BezierCanvas::new(self.width, self.height);
+        canvas.pixels.par_iter_mut()
+            .zip(self.pixels.par_iter().zip(other.pixels.par_iter()))
+            .for_each(|(pixel, (a, b))| {
+                let d = ExternalType::from_value(*a).to_vec4() - ExternalType::from_value(*b).to_vec4();
+                let alpha = d.w().abs();
+                let mut color = Vec4::zero();
+                for i in 0..3 {
+                    color.v[i] = (d.v[i].abs() + alpha).min(1.0);
+                }
+                color.v[3] = 1.0;
+                *pixel = ExternalType::from_vec4(color).to_value();
+            });
+        canvas
+    }
+}
+
+/*
+    Assert that a canvas matches a golden PNG up to `tolerance`, the largest error allowed on a channel in [0, 1].
+
+    On failure, the canvas and the diff image are written next to the golden PNG, as `.actual.png` and `.diff.png`.
+ */
+pub fn assert_canvas_matches<InternalType: InternalColorType, ExternalType: ColorType<InternalType>>(canvas: &BezierCanvas<InternalType, ExternalType>, golden_png: &str, tolerance: f32)
+    where BezierCanvas<InternalType, ExternalType>: PNGCompatible {
+    let stem = golden_png.strip_suffix(".png").unwrap_or(golden_png);
+    let actual_png = format!("{}.actual.png", stem);
+    if !std::path::Path::new(golden_png).exists() {
+        canvas.export_png(&actual_png);
+        panic!("golden image {} not found, canvas written to {}", golden_png, actual_png);
+    }
+    let golden = BezierCanvas::<InternalType, ExternalType>::from_png(golden_png);
+    if golden.width != canvas.width || golden.height != canvas.height {
+        canvas.export_png(&actual_png);
+        panic!("canvas is {}x{} but {} is {}x{}, canvas written to {}",
+            canvas.width, canvas.height, golden_png, golden.width, golden.height, actual_png);
+    }
+    let comparison = canvas.compare(&golden);
+    if comparison.max_error > tolerance {
+        let diff_png = format!("{}.diff.png", stem);
+        canvas.export_png(&actual_png);
+        canvas.diff(&golden).export_png(&diff_png);
+        panic!("canvas does not match {} (tolerance {}): {:?}, canvas written to {} and diff to {}",
+            golden_png, tolerance, comparison, actual_png, diff_png);
+    }
+}
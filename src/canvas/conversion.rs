@@ -0,0 +1,39 @@
+use rayon::prelude::*;
+
+use crate::linalg::Vec4;
+use crate::types::{
+    colortype::{InternalColorType, ColorType},
+    conversion::Conversion
+};
+use crate::canvas::BezierCanvas;
+
+impl <InternalType: InternalColorType, ExternalType: ColorType<InternalType>> BezierCanvas<InternalType, ExternalType> {
+    // convert to another color type through `to_vec4` and `from_vec4`
+    pub fn convert<InternalType2: InternalColorType, ExternalType2: ColorType<InternalType2>>(&self) -> BezierCanvas<InternalType2, ExternalType2> {
+        self.convert_with(Conversion::default())
+    }
+
+    pub fn convert_with<InternalType2: InternalColorType, ExternalType2: ColorType<InternalType2>>(&self, conversion: Conversion) -> BezierCanvas<InternalType2, ExternalType2> {
+        let mut canvas = BezierCanvas::new(self.width, self.height);
+        canvas.working_space = self.working_space;
+        canvas.dither = self.dither;
+        let space = self.working_space;
+        let background = conversion.background.map(|background| space.decode(background));
+        canvas.pixels.par_iter_mut()
+            .zip(self.pixels.par_iter())
+            .for_each(|(dst, src)| {
+                let mut color = ExternalType::from_value(*src).to_vec4();
+                if let Some(background) = background {
+                    let fg = space.decode(color);
+                    color = space.encode(fg * fg.w() + background * (1.0 - fg.w()));
+                    color.v[3] = 1.0;
+                }
+                if let Some(luma) = conversion.grayscale {
+                    let l = luma.luma(color);
+                    color = Vec4::new(l, l, l, color.w());
+                }
+                *dst = ExternalType2::from_vec4(color).to_value();
+            });
+        canvas
+    }
+}
@@ -1,8 +1,10 @@
 use std::{path::Path, fs::File, io::BufWriter};
 use crate::canvas::BezierCanvas;
-use crate::convert::PNGCompatible;
-use crate::types::colortype::{ColorType, RGBA, RGB, RA, R, A};
-fn init_writer(img_path: &str, width: u32, height: u32, color_type: png::ColorType) -> png::Writer<BufWriter<File>> {
+use crate::convert::{PNGCompatible, PNGSamples};
+use rayon::prelude::*;
+
+use crate::types::colortype::{InternalColorType, ColorType, RGBA, RGB, RA, R, A, PremulRGBA, RGBA16, RGB16, RA16, R16};
+fn init_encoder(img_path: &str, width: u32, height: u32, color_type: png::ColorType, bit_depth: png::BitDepth) -> png::Encoder<'static, BufWriter<File>> {
     let path = Path::new(img_path);
     let file = File::create(path).unwrap();
 
@@ -10,9 +12,10 @@ fn init_writer(img_path: &str, width: u32, height: u32, color_type: png::ColorTy
 
     let mut encoder = png::Encoder::new(w, width, height);
     encoder.set_color(color_type);
-    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_depth(bit_depth);
+    // stored colors are sRGB encoded, gAMA and cHRM are the fallback values of the sRGB chunk
+    encoder.set_source_srgb(png::SrgbRenderingIntent::Perceptual);
     encoder.set_source_gamma(png::ScaledFloat::from_scaled(45455)); // 1.0 / 2.2, scaled by 100000
-    encoder.set_source_gamma(png::ScaledFloat::new(1.0 / 2.2));     // 1.0 / 2.2, unscaled, but rounded
     let source_chromaticities = png::SourceChromaticities::new(     // Using unscaled instantiation here
         (0.31270, 0.32900),
         (0.64000, 0.33000),
@@ -20,26 +23,51 @@ fn init_writer(img_path: &str, width: u32, height: u32, color_type: png::ColorTy
         (0.15000, 0.06000)
     );
     encoder.set_source_chromaticities(source_chromaticities);
-    encoder.write_header().unwrap()
+    encoder
+}
+fn init_writer(img_path: &str, width: u32, height: u32, color_type: png::ColorType, bit_depth: png::BitDepth) -> png::Writer<BufWriter<File>> {
+    init_encoder(img_path, width, height, color_type, bit_depth).write_header().unwrap()
+}
+
+/*
+    Decode any PNG into 16-bit RGBA samples; samples of fewer bits are scaled to the full 16-bit range.
+    Palettes are resolved, gray is expanded to all three color channels, and alpha is opaque if the image has none.
+    The color type returned is the one after expansion, with alpha if the image has a tRNS chunk.
+ */
+fn decode_png16(img_path: &str) -> (usize, usize, png::ColorType, Vec<[u16; 4]>) {
+    let mut decoder = png::Decoder::new(File::open(img_path).unwrap());
+    // bit depths below 8 are expanded to 8, palettes and tRNS chunks to RGB(A)
+    decoder.set_transformations(png::Transformations::EXPAND);
+    let mut reader = decoder.read_info().unwrap();
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buf).unwrap();
+    let bytes = &buf[..info.buffer_size()];
+    let channels = info.color_type.samples();
+    let sample = |i: usize| match info.bit_depth {
+        png::BitDepth::Sixteen => u16::from_be_bytes([bytes[2 * i], bytes[2 * i + 1]]),
+        _ => bytes[i] as u16 * 257,
+    };
+    let samples = (0..info.width as usize * info.height as usize).map(|p| {
+        let c = |k: usize| sample(p * channels + k);
+        match info.color_type {
+            png::ColorType::Rgba => [c(0), c(1), c(2), c(3)],
+            png::ColorType::Rgb => [c(0), c(1), c(2), 0xffff],
+            png::ColorType::GrayscaleAlpha => [c(0), c(0), c(0), c(1)],
+            png::ColorType::Grayscale => [c(0), c(0), c(0), 0xffff],
+            png::ColorType::Indexed => unreachable!("palettes are expanded by the decoder"),
+        }
+    }).collect();
+    (info.width as usize, info.height as usize, info.color_type, samples)
 }
 
 impl PNGCompatible for BezierCanvas<u32, RGBA> {
     fn export_png(&self, img_path: &str) {
-        let ref mut writer = init_writer(img_path, self.width as u32, self.height as u32, png::ColorType::Rgba);
-
-        let mut data: Vec<u8> = vec![0; 4 * self.width * self.height];
-        for y in 0..self.height {
-            for x in 0..self.width {
-                data[(y * self.width + x) * 4] = self.get_pixel(x, y).r;
-                data[(y * self.width + x) * 4 + 1] = self.get_pixel(x, y).g;
-                data[(y * self.width + x) * 4 + 2] = self.get_pixel(x, y).b;
-                data[(y * self.width + x) * 4 + 3] = self.get_pixel(x, y).a;
-            }
-        }
-        writer.write_image_data(&data).unwrap();
+        self.export_png_samples(img_path);
     }
     fn from_png(img_path: &str) -> Self {
-        let decoder = png::Decoder::new(File::open(img_path).unwrap());
+        let mut decoder = png::Decoder::new(File::open(img_path).unwrap());
+        // resolve palettes and tRNS chunks into RGB(A)
+        decoder.set_transformations(png::Transformations::EXPAND);
         let mut reader = decoder.read_info().unwrap();
         let mut buf = vec![0; reader.output_buffer_size()];
         let info = reader.next_frame(&mut buf).unwrap();
@@ -83,18 +111,7 @@ impl PNGCompatible for BezierCanvas<u32, RGBA> {
                     }
                 }
             }
-            png::ColorType::Indexed => {
-                for y in 0..canvas.height {
-                    for x in 0..canvas.width {
-                        canvas.pixels[y * canvas.width + x] = RGBA {
-                            r: 0,
-                            g: 0,
-                            b: 0,
-                            a: bytes[y * canvas.width + x],
-                        }.to_value();
-                    }
-                }
-            },
+            png::ColorType::Indexed => unreachable!("palettes are expanded by the decoder"),
             png::ColorType::GrayscaleAlpha => {
                 for y in 0..canvas.height {
                     for x in 0..canvas.width {
@@ -111,23 +128,29 @@ impl PNGCompatible for BezierCanvas<u32, RGBA> {
         canvas
     }
 }
+impl BezierCanvas<u32, RGBA> {
+    /*
+        Export as an indexed PNG with the given palette of at most 256 colors, e.g. from `palette`.
+        Palette alpha is written as a tRNS chunk when any color is not opaque.
+     */
+    pub fn export_indexed_png(&self, img_path: &str, palette: &[RGBA]) {
+        let mut encoder = init_encoder(img_path, self.width as u32, self.height as u32, png::ColorType::Indexed, png::BitDepth::Eight);
+        encoder.set_palette(palette.iter().flat_map(|c| [c.r, c.g, c.b]).collect::<Vec<u8>>());
+        if palette.iter().any(|c| c.a != 255) {
+            encoder.set_trns(palette.iter().map(|c| c.a).collect::<Vec<u8>>());
+        }
+        let writer = &mut encoder.write_header().unwrap();
+        writer.write_image_data(&self.map_to_palette(palette)).unwrap();
+    }
+}
 impl PNGCompatible for BezierCanvas<u32, RGB> {
     fn export_png(&self, img_path: &str) {
-        let ref mut writer = init_writer(img_path, self.width as u32, self.height as u32, png::ColorType::Rgb);
-
-        let mut data: Vec<u8> = vec![0; 3 * self.width * self.height];
-        for y in 0..self.height {
-            for x in 0..self.width {
-                data[(y * self.width + x) * 3] = self.get_pixel(x, y).r;
-                data[(y * self.width + x) * 3 + 1] = self.get_pixel(x, y).g;
-                data[(y * self.width + x) * 3 + 2] = self.get_pixel(x, y).b;
-            }
-        }
-        writer.write_image_data(&data).unwrap();
-
+        self.export_png_samples(img_path);
     }
     fn from_png(img_path: &str) -> Self {
-        let decoder = png::Decoder::new(File::open(img_path).unwrap());
+        let mut decoder = png::Decoder::new(File::open(img_path).unwrap());
+        // resolve palettes and tRNS chunks into RGB(A)
+        decoder.set_transformations(png::Transformations::EXPAND);
         let mut reader = decoder.read_info().unwrap();
         let mut buf = vec![0; reader.output_buffer_size()];
         let info = reader.next_frame(&mut buf).unwrap();
@@ -157,24 +180,37 @@ impl PNGCompatible for BezierCanvas<u32, RGB> {
                     }
                 }
             }
-            _ => panic!("Incompatible color type"),
+            // alpha, e.g. of a tRNS chunk, is dropped
+            png::ColorType::Rgba => {
+                for y in 0..canvas.height {
+                    for x in 0..canvas.width {
+                        canvas.pixels[y * canvas.width + x] = RGB {
+                            r: bytes[(y * canvas.width + x) * 4],
+                            g: bytes[(y * canvas.width + x) * 4 + 1],
+                            b: bytes[(y * canvas.width + x) * 4 + 2],
+                        }.to_value();
+                    }
+                }
+            }
+            png::ColorType::GrayscaleAlpha => {
+                for y in 0..canvas.height {
+                    for x in 0..canvas.width {
+                        canvas.pixels[y * canvas.width + x] = RGB {
+                            r: bytes[(y * canvas.width + x) * 2],
+                            g: bytes[(y * canvas.width + x) * 2],
+                            b: bytes[(y * canvas.width + x) * 2],
+                        }.to_value();
+                    }
+                }
+            }
+            png::ColorType::Indexed => unreachable!("palettes are expanded by the decoder"),
         }
         canvas
     }
 }
 impl PNGCompatible for BezierCanvas<u16, RA> {
     fn export_png(&self, img_path: &str) {
-        let ref mut writer = init_writer(img_path, self.width as u32, self.height as u32, png::ColorType::GrayscaleAlpha);
-
-        let mut data: Vec<u8> = vec![0; 3 * self.width * self.height];
-        for y in 0..self.height {
-            for x in 0..self.width {
-                data[(y * self.width + x) * 2] = self.get_pixel(x, y).r;
-                data[(y * self.width + x) * 2 + 1] = self.get_pixel(x, y).a;
-            }
-        }
-        writer.write_image_data(&data).unwrap();
-
+        self.export_png_samples(img_path);
     }
     fn from_png(img_path: &str) -> Self {
         let decoder = png::Decoder::new(File::open(img_path).unwrap());
@@ -222,16 +258,7 @@ impl PNGCompatible for BezierCanvas<u16, RA> {
 }
 impl PNGCompatible for BezierCanvas<u8, R> {
     fn export_png(&self, img_path: &str) {
-        let ref mut writer = init_writer(img_path, self.width as u32, self.height as u32, png::ColorType::Grayscale);
-
-        let mut data: Vec<u8> = vec![0; 3 * self.width * self.height];
-        for y in 0..self.height {
-            for x in 0..self.width {
-                data[y * self.width + x] = self.get_pixel(x, y).r;
-            }
-        }
-        writer.write_image_data(&data).unwrap();
-
+        self.export_png_samples(img_path);
     }
     fn from_png(img_path: &str) -> Self {
         let decoder = png::Decoder::new(File::open(img_path).unwrap());
@@ -258,16 +285,7 @@ impl PNGCompatible for BezierCanvas<u8, R> {
 }
 impl PNGCompatible for BezierCanvas<u8, A> {
     fn export_png(&self, img_path: &str) {
-        let ref mut writer = init_writer(img_path, self.width as u32, self.height as u32, png::ColorType::Indexed);
-
-        let mut data: Vec<u8> = vec![0; 3 * self.width * self.height];
-        for y in 0..self.height {
-            for x in 0..self.width {
-                data[y * self.width + x] = self.get_pixel(x, y).a;
-            }
-        }
-        writer.write_image_data(&data).unwrap();
-
+        self.export_png_samples(img_path);
     }
     fn from_png(img_path: &str) -> Self {
         let decoder = png::Decoder::new(File::open(img_path).unwrap());
@@ -291,4 +309,191 @@ impl PNGCompatible for BezierCanvas<u8, A> {
         }
         canvas
     }
-}
\ No newline at end of file
+}
+// premultiplied canvases are stored in PNG as straight alpha
+impl PNGCompatible for BezierCanvas<u32, PremulRGBA> {
+    fn export_png(&self, img_path: &str) {
+        self.export_png_samples(img_path);
+    }
+    fn from_png(img_path: &str) -> Self {
+        let straight = BezierCanvas::<u32, RGBA>::from_png(img_path);
+        let mut canvas = BezierCanvas::new(straight.width, straight.height);
+        canvas.pixels.par_iter_mut()
+            .zip(straight.pixels.par_iter())
+            .for_each(|(dst, src)| {
+                *dst = PremulRGBA::from_vec4(RGBA::from_value(*src).to_vec4()).to_value();
+            });
+        canvas
+    }
+}
+impl PNGCompatible for BezierCanvas<u64, RGBA16> {
+    fn export_png(&self, img_path: &str) {
+        self.export_png_samples(img_path);
+    }
+    fn from_png(img_path: &str) -> Self {
+        let (width, height, _, samples) = decode_png16(img_path);
+        let mut canvas = BezierCanvas::new(width, height);
+        for (pixel, [r, g, b, a]) in canvas.pixels.iter_mut().zip(samples) {
+            *pixel = RGBA16 { r, g, b, a }.to_value();
+        }
+        canvas
+    }
+}
+impl PNGCompatible for BezierCanvas<u64, RGB16> {
+    fn export_png(&self, img_path: &str) {
+        self.export_png_samples(img_path);
+    }
+    fn from_png(img_path: &str) -> Self {
+        // alpha, e.g. of a tRNS chunk, is dropped
+        let (width, height, _, samples) = decode_png16(img_path);
+        let mut canvas = BezierCanvas::new(width, height);
+        for (pixel, [r, g, b, _]) in canvas.pixels.iter_mut().zip(samples) {
+            *pixel = RGB16 { r, g, b }.to_value();
+        }
+        canvas
+    }
+}
+impl PNGCompatible for BezierCanvas<u32, RA16> {
+    fn export_png(&self, img_path: &str) {
+        self.export_png_samples(img_path);
+    }
+    fn from_png(img_path: &str) -> Self {
+        let (width, height, color_type, samples) = decode_png16(img_path);
+        match color_type {
+            png::ColorType::GrayscaleAlpha | png::ColorType::Grayscale => {},
+            _ => panic!("Incompatible color type"),
+        }
+        let mut canvas = BezierCanvas::new(width, height);
+        for (pixel, [r, _, _, a]) in canvas.pixels.iter_mut().zip(samples) {
+            *pixel = RA16 { r, a }.to_value();
+        }
+        canvas
+    }
+}
+impl PNGCompatible for BezierCanvas<u16, R16> {
+    fn export_png(&self, img_path: &str) {
+        self.export_png_samples(img_path);
+    }
+    fn from_png(img_path: &str) -> Self {
+        let (width, height, color_type, samples) = decode_png16(img_path);
+        // alpha, e.g. of a tRNS chunk, is dropped
+        match color_type {
+            png::ColorType::Grayscale | png::ColorType::GrayscaleAlpha => {},
+            _ => panic!("Incompatible color type"),
+        }
+        let mut canvas = BezierCanvas::new(width, height);
+        for (pixel, [r, _, _, _]) in canvas.pixels.iter_mut().zip(samples) {
+            *pixel = R16 { r }.to_value();
+        }
+        canvas
+    }
+}
+
+impl PNGSamples for RGBA {
+    fn png_format() -> (png::ColorType, png::BitDepth) {
+        (png::ColorType::Rgba, png::BitDepth::Eight)
+    }
+    fn write_samples(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&[self.r, self.g, self.b, self.a]);
+    }
+}
+impl PNGSamples for PremulRGBA {
+    fn png_format() -> (png::ColorType, png::BitDepth) {
+        RGBA::png_format()
+    }
+    fn write_samples(&self, out: &mut Vec<u8>) {
+        RGBA::from_vec4(self.to_vec4()).write_samples(out);
+    }
+}
+impl PNGSamples for RGB {
+    fn png_format() -> (png::ColorType, png::BitDepth) {
+        (png::ColorType::Rgb, png::BitDepth::Eight)
+    }
+    fn write_samples(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&[self.r, self.g, self.b]);
+    }
+}
+impl PNGSamples for RA {
+    fn png_format() -> (png::ColorType, png::BitDepth) {
+        (png::ColorType::GrayscaleAlpha, png::BitDepth::Eight)
+    }
+    fn write_samples(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&[self.r, self.a]);
+    }
+}
+impl PNGSamples for R {
+    fn png_format() -> (png::ColorType, png::BitDepth) {
+        (png::ColorType::Grayscale, png::BitDepth::Eight)
+    }
+    fn write_samples(&self, out: &mut Vec<u8>) {
+        out.push(self.r);
+    }
+}
+// alpha as palette indices, as `from_png` reads it
+impl PNGSamples for A {
+    fn png_format() -> (png::ColorType, png::BitDepth) {
+        (png::ColorType::Indexed, png::BitDepth::Eight)
+    }
+    fn write_samples(&self, out: &mut Vec<u8>) {
+        out.push(self.a);
+    }
+}
+impl PNGSamples for RGBA16 {
+    fn png_format() -> (png::ColorType, png::BitDepth) {
+        (png::ColorType::Rgba, png::BitDepth::Sixteen)
+    }
+    fn write_samples(&self, out: &mut Vec<u8>) {
+        out.extend([self.r, self.g, self.b, self.a].iter().flat_map(|c| c.to_be_bytes()));
+    }
+}
+impl PNGSamples for RGB16 {
+    fn png_format() -> (png::ColorType, png::BitDepth) {
+        (png::ColorType::Rgb, png::BitDepth::Sixteen)
+    }
+    fn write_samples(&self, out: &mut Vec<u8>) {
+        out.extend([self.r, self.g, self.b].iter().flat_map(|c| c.to_be_bytes()));
+    }
+}
+impl PNGSamples for RA16 {
+    fn png_format() -> (png::ColorType, png::BitDepth) {
+        (png::ColorType::GrayscaleAlpha, png::BitDepth::Sixteen)
+    }
+    fn write_samples(&self, out: &mut Vec<u8>) {
+        out.extend([self.r, self.a].iter().flat_map(|c| c.to_be_bytes()));
+    }
+}
+impl PNGSamples for R16 {
+    fn png_format() -> (png::ColorType, png::BitDepth) {
+        (png::ColorType::Grayscale, png::BitDepth::Sixteen)
+    }
+    fn write_samples(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.r.to_be_bytes());
+    }
+}
+
+impl <InternalType: InternalColorType, ExternalType: ColorType<InternalType> + PNGSamples> BezierCanvas<InternalType, ExternalType> {
+    fn export_png_samples(&self, img_path: &str) {
+        export_png_rows::<ExternalType, _>(img_path, self.width, self.height, |y, row| {
+            row.extend(self.pixels[y * self.width..(y + 1) * self.width].iter().map(|p| ExternalType::from_value(*p)));
+        });
+    }
+}
+
+// stream rows of pixels into a PNG, so images never have to be held in memory at once
+pub(crate) fn export_png_rows<ExternalType: PNGSamples, Rows: FnMut(usize, &mut Vec<ExternalType>)>(img_path: &str, width: usize, height: usize, mut rows: Rows) {
+    let (color_type, bit_depth) = ExternalType::png_format();
+    let mut writer = init_writer(img_path, width as u32, height as u32, color_type, bit_depth);
+    let mut stream = writer.stream_writer().unwrap();
+    let mut row = Vec::with_capacity(width);
+    let mut bytes = Vec::new();
+    for y in 0..height {
+        row.clear();
+        rows(y, &mut row);
+        bytes.clear();
+        for pixel in &row {
+            pixel.write_samples(&mut bytes);
+        }
+        std::io::Write::write_all(&mut stream, &bytes).unwrap();
+    }
+    stream.finish().unwrap();
+}
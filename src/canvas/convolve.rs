@@ -0,0 +1,142 @@
+use num::Zero;
+use rayon::prelude::*;
+
+use crate::linalg::Vec4;
+use crate::texture::{Wrapping, WrapClampToEdge};
+use crate::types::{
+    colortype::{InternalColorType, ColorType, unpremultiply},
+    kernel::{Kernel, EdgeOperator}
+};
+use crate::canvas::BezierCanvas;
+use crate::canvas::blur::{gaussian_kernel, separable, convolve};
+
+impl <InternalType: InternalColorType, ExternalType: ColorType<InternalType>> BezierCanvas<InternalType, ExternalType> {
+    /*
+        Convolve with a kernel into a new canvas, pixels outside the canvas are read through the wrapping modes.
+        The kernel is applied as is, without flipping.
+
+        Premultiplied colors are convolved, alpha included, which suits kernels with weights summing to one.
+        With `preserve_alpha`, straight colors are convolved and alpha is kept instead, e.g. for edge detection.
+     */
+    pub fn convolve<WrapS: Wrapping, WrapT: Wrapping>(&self, kernel: &Kernel, preserve_alpha: bool) -> Self {
+        let colors = self.working_colors(!preserve_alpha);
+        let mut filtered = self.correlate::<WrapS, WrapT>(&colors, kernel);
+        if preserve_alpha {
+            filtered.par_iter_mut()
+                .zip(colors.par_iter())
+                .for_each(|(color, original)| color.v[3] = original.w());
+        }
+        self.with_colors(&filtered, !preserve_alpha)
+    }
+
+    pub fn sharpen(&self) -> Self {
+        self.convolve::<WrapClampToEdge, WrapClampToEdge>(&Kernel::sharpen(), false)
+    }
+
+    pub fn emboss(&self) -> Self {
+        self.convolve::<WrapClampToEdge, WrapClampToEdge>(&Kernel::emboss(), true)
+    }
+
+    // absolute response of the laplacian of every channel
+    pub fn laplacian(&self) -> Self {
+        let colors = self.working_colors(false);
+        let mut filtered = self.correlate::<WrapClampToEdge, WrapClampToEdge>(&colors, &Kernel::laplacian());
+        filtered.par_iter_mut()
+            .zip(colors.par_iter())
+            .for_each(|(color, original)| {
+                *color = Vec4::new(color.x().abs(), color.y().abs(), color.z().abs(), original.w());
+            });
+        self.with_colors(&filtered, false)
+    }
+
+    // gradient magnitude of every channel
+    pub fn edge_detect(&self, operator: EdgeOperator) -> Self {
+        let colors = self.working_colors(false);
+        let (kernel_x, kernel_y) = Kernel::gradient(operator);
+        let gx = self.correlate::<WrapClampToEdge, WrapClampToEdge>(&colors, &kernel_x);
+        let gy = self.correlate::<WrapClampToEdge, WrapClampToEdge>(&colors, &kernel_y);
+        let magnitude: Vec<Vec4> = gx.par_iter()
+            .zip(gy.par_iter())
+            .zip(colors.par_iter())
+            .map(|((gx, gy), original)| {
+                let mut ans = gx.star(gx) + gy.star(gy);
+                for c in ans.v.iter_mut().take(3) {
+                    *c = c.sqrt();
+                }
+                ans.v[3] = original.w();
+                ans
+            })
+            .collect();
+        self.with_colors(&magnitude, false)
+    }
+
+    // add `amount` times the difference to a gaussian blur of `sigma` pixels
+    pub fn unsharp_mask(&self, sigma: f32, amount: f32) -> Self {
+        let colors = self.working_colors(true);
+        if sigma <= 0.0 {
+            return self.with_colors(&colors, true);
+        }
+        let kernel = gaussian_kernel(sigma);
+        let blurred = separable(&colors, self.width, self.height, |src, dst| convolve(src, dst, &kernel));
+        let sharpened: Vec<Vec4> = colors.par_iter()
+            .zip(blurred.par_iter())
+            .map(|(color, blurred)| *color + (*color - *blurred) * amount)
+            .collect();
+        self.with_colors(&sharpened, true)
+    }
+
+    // colors decoded to the working space, premultiplied or not
+    fn working_colors(&self, premultiplied: bool) -> Vec<Vec4> {
+        let space = self.working_space;
+        self.pixels.par_iter()
+            .map(|pixel| {
+                let color = ExternalType::from_value(*pixel);
+                if premultiplied { space.to_premul_vec4(&color) } else { space.to_vec4(&color) }
+            })
+            .collect()
+    }
+
+    fn correlate<WrapS: Wrapping, WrapT: Wrapping>(&self, colors: &[Vec4], kernel: &Kernel) -> Vec<Vec4> {
+        let (width, height) = (self.width, self.height);
+        if width == 0 || height == 0 {
+            return Vec::new();
+        }
+        let (radius_x, radius_y) = ((kernel.width / 2) as isize, (kernel.height / 2) as isize);
+        let mut ans = vec![Vec4::zero(); width * height];
+        ans.par_chunks_mut(width)
+            .enumerate()
+            .for_each(|(y, row)| {
+                for (x, pixel) in row.iter_mut().enumerate() {
+                    let mut sum = Vec4::zero();
+                    for (k, weight) in kernel.weights.iter().enumerate() {
+                        if *weight == 0.0 {
+                            continue;
+                        }
+                        let sx = WrapS::wrap_index(x as isize + (k % kernel.width) as isize - radius_x, width);
+                        let sy = WrapT::wrap_index(y as isize + (k / kernel.width) as isize - radius_y, height);
+                        sum = sum + colors[sy * width + sx] * *weight;
+                    }
+                    *pixel = sum;
+                }
+            });
+        ans
+    }
+
+    // a new canvas of filtered working space colors, keeping alpha valid and colors non-negative
+    fn with_colors(&self, colors: &[Vec4], premultiplied: bool) -> Self {
+        let space = self.working_space;
+        let mut canvas = BezierCanvas::new(self.width, self.height);
+        canvas.working_space = space;
+        canvas.pixels.par_iter_mut()
+            .zip(colors.par_iter())
+            .for_each(|(pixel, color)| {
+                let mut color = if premultiplied { unpremultiply(*color) } else { *color };
+                for c in color.v.iter_mut().take(3) {
+                    *c = c.max(0.0);
+                }
+                color.v[3] = color.w().clamp(0.0, 1.0);
+                *pixel = space.from_vec4::<InternalType, ExternalType>(color).to_value();
+            });
+        canvas
+    }
+}
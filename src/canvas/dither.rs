@@ -0,0 +1,57 @@
+use rayon::prelude::*;
+
+use num::Zero;
+
+use crate::linalg::Vec4;
+use crate::types::{
+    colortype::{InternalColorType, ColorType},
+    dither::Dither
+};
+use crate::canvas::BezierCanvas;
+
+impl <InternalType: InternalColorType, ExternalType: ColorType<InternalType>> BezierCanvas<InternalType, ExternalType> {
+    /*
+        Convert to a color type of lower precision, e.g. from a float or 16-bit canvas to an 8-bit one, with dithering.
+     */
+    pub fn quantize<InternalType2: InternalColorType, ExternalType2: ColorType<InternalType2>>(&self, dither: Dither) -> BezierCanvas<InternalType2, ExternalType2> {
+        let mut canvas = BezierCanvas::new(self.width, self.height);
+        canvas.working_space = self.working_space;
+        if self.width == 0 || self.height == 0 {
+            return canvas;
+        }
+        let width = self.width;
+        match dither {
+            Dither::FloydSteinberg => {
+                // error diffused to the right (7/16), bottom-left (3/16), bottom (5/16) and bottom-right (1/16)
+                let mut error = vec![Vec4::zero(); width + 2];
+                let mut next_error = vec![Vec4::zero(); width + 2];
+                for (src_row, dst_row) in self.pixels.chunks(width).zip(canvas.pixels.chunks_mut(width)) {
+                    for x in 0..width {
+                        let wanted = ExternalType::from_value(src_row[x]).to_vec4() + error[x + 1];
+                        let quantized = ExternalType2::from_vec4(wanted);
+                        let residual = wanted - quantized.to_vec4();
+                        dst_row[x] = quantized.to_value();
+                        error[x + 2] = error[x + 2] + residual * (7.0 / 16.0);
+                        next_error[x] = next_error[x] + residual * (3.0 / 16.0);
+                        next_error[x + 1] = next_error[x + 1] + residual * (5.0 / 16.0);
+                        next_error[x + 2] = next_error[x + 2] + residual * (1.0 / 16.0);
+                    }
+                    error = next_error;
+                    next_error = vec![Vec4::zero(); width + 2];
+                }
+            },
+            _ => {
+                canvas.pixels.par_chunks_mut(width)
+                    .zip(self.pixels.par_chunks(width))
+                    .enumerate()
+                    .for_each(|(y, (dst_row, src_row))| {
+                        for x in 0..width {
+                            let raw = ExternalType::from_value(src_row[x]).to_vec4();
+                            dst_row[x] = dither.quantize::<InternalType2, ExternalType2>(raw, x, y).to_value();
+                        }
+                    });
+            },
+        }
+        canvas
+    }
+}
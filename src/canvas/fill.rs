@@ -0,0 +1,89 @@
+use rayon::prelude::*;
+
+use crate::types::{
+    colortype::{InternalColorType, ColorType, A},
+    blend::BlendMode,
+    fill::Connectivity
+};
+use crate::canvas::{BezierCanvas, coverage};
+
+impl <InternalType: InternalColorType, ExternalType: ColorType<InternalType>> BezierCanvas<InternalType, ExternalType> {
+    /*
+        Fill the region connected to pixel (x, y) whose colors are within `tolerance` of its color,
+        the largest difference of a channel of straight colors in [0, 1].
+     */
+    pub fn flood_fill(&mut self, x: usize, y: usize, tolerance: f32, connectivity: Connectivity, color: &ExternalType, blend_mode: BlendMode) {
+        let region = self.flood_region(x, y, tolerance, connectivity);
+        let space = self.working_space;
+        let mask = self.mask.as_deref();
+        self.pixels.par_iter_mut()
+            .zip(region.par_iter())
+            .enumerate()
+            .filter(|(_, (_, inside))| **inside)
+            .for_each(|(i, (pixel, _))| BezierCanvas::par_set_pixel(pixel, color, blend_mode, space, coverage(mask, i)));
+    }
+
+    // the region `flood_fill` would fill, as an opaque mask usable with `set_mask`
+    pub fn flood_select(&self, x: usize, y: usize, tolerance: f32, connectivity: Connectivity) -> BezierCanvas<u8, A> {
+        let region = self.flood_region(x, y, tolerance, connectivity);
+        let mut mask = BezierCanvas::new(self.width, self.height);
+        mask.pixels.par_iter_mut()
+            .zip(region.par_iter())
+            .for_each(|(pixel, inside)| *pixel = if *inside { 255 } else { 0 });
+        mask
+    }
+
+    /*
+        Scanline flood fill: every seed is extended to the whole matching span of its row,
+        and one seed is pushed for every matching run of the rows above and below the span.
+     */
+    fn flood_region(&self, x: usize, y: usize, tolerance: f32, connectivity: Connectivity) -> Vec<bool> {
+        let (width, height) = (self.width, self.height);
+        assert!(x < width && y < height);
+        let target = self.get_pixel(x, y).to_vec4();
+        let matches = |i: usize| {
+            let d = ExternalType::from_value(self.pixels[i]).to_vec4() - target;
+            d.v.iter().all(|c| c.abs() <= tolerance)
+        };
+        let mut region = vec![false; width * height];
+        let mut seeds = vec![(x, y)];
+        while let Some((x, y)) = seeds.pop() {
+            let row = y * width;
+            if region[row + x] {
+                continue;
+            }
+            let mut left = x;
+            while left > 0 && !region[row + left - 1] && matches(row + left - 1) {
+                left -= 1;
+            }
+            let mut right = x;
+            while right + 1 < width && !region[row + right + 1] && matches(row + right + 1) {
+                right += 1;
+            }
+            region[row + left..=row + right].fill(true);
+
+            let (scan_left, scan_right) = match connectivity {
+                Connectivity::Four => (left, right),
+                Connectivity::Eight => (left.saturating_sub(1), (right + 1).min(width - 1)),
+            };
+            for ny in [y.wrapping_sub(1), y + 1] {
+                if ny >= height {
+                    continue;
+                }
+                let mut in_run = false;
+                for nx in scan_left..=scan_right {
+                    let i = ny * width + nx;
+                    if !region[i] && matches(i) {
+                        if !in_run {
+                            seeds.push((nx, ny));
+                            in_run = true;
+                        }
+                    } else {
+                        in_run = false;
+                    }
+                }
+            }
+        }
+        region
+    }
+}
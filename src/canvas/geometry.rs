@@ -0,0 +1,98 @@
+use rayon::prelude::*;
+
+use crate::types::colortype::{InternalColorType, ColorType};
+use crate::canvas::BezierCanvas;
+
+/*
+    Lossless geometric operations, copying the internal values into a new canvas.
+    Positions and sizes are in pixels, rotations are clockwise.
+ */
+impl <InternalType: InternalColorType, ExternalType: ColorType<InternalType>> BezierCanvas<InternalType, ExternalType> {
+    // the rect is clipped to the canvas
+    pub fn crop(&self, x: usize, y: usize, width: usize, height: usize) -> Self {
+        let x = x.min(self.width);
+        let y = y.min(self.height);
+        let width = width.min(self.width - x);
+        let height = height.min(self.height - y);
+        let mut canvas = self.with_size(width, height);
+        if width == 0 {
+            return canvas;
+        }
+        canvas.pixels.par_chunks_mut(width)
+            .zip(self.pixels.par_chunks(self.width).skip(y))
+            .for_each(|(dst, src)| dst.copy_from_slice(&src[x..x + width]));
+        canvas
+    }
+
+    pub fn pad(&self, left: usize, top: usize, right: usize, bottom: usize, fill: &ExternalType) -> Self {
+        let width = left + self.width + right;
+        let mut canvas = self.with_size(width, top + self.height + bottom);
+        if width == 0 {
+            return canvas;
+        }
+        let fill = fill.to_value();
+        let src_width = self.width;
+        canvas.pixels.par_chunks_mut(width)
+            .enumerate()
+            .for_each(|(y, dst)| {
+                if y < top || y >= top + self.height {
+                    dst.fill(fill);
+                    return;
+                }
+                dst[..left].fill(fill);
+                dst[left..left + src_width].copy_from_slice(&self.pixels[(y - top) * src_width..(y - top + 1) * src_width]);
+                dst[left + src_width..].fill(fill);
+            });
+        canvas
+    }
+
+    pub fn flip_horizontal(&self) -> Self {
+        self.remap(self.width, self.height, |x, y| (self.width - 1 - x, y))
+    }
+
+    pub fn flip_vertical(&self) -> Self {
+        self.remap(self.width, self.height, |x, y| (x, self.height - 1 - y))
+    }
+
+    pub fn rotate_90(&self) -> Self {
+        self.remap(self.height, self.width, |x, y| (y, self.height - 1 - x))
+    }
+
+    pub fn rotate_180(&self) -> Self {
+        self.remap(self.width, self.height, |x, y| (self.width - 1 - x, self.height - 1 - y))
+    }
+
+    pub fn rotate_270(&self) -> Self {
+        self.remap(self.height, self.width, |x, y| (self.width - 1 - y, x))
+    }
+
+    // mirror along the main diagonal
+    pub fn transpose(&self) -> Self {
+        self.remap(self.height, self.width, |x, y| (y, x))
+    }
+
+    // an empty canvas with the same settings
+    fn with_size(&self, width: usize, height: usize) -> Self {
+        let mut canvas = BezierCanvas::new(width, height);
+        canvas.working_space = self.working_space;
+        canvas.dither = self.dither;
+        canvas
+    }
+
+    // a new canvas with every pixel copied from the source pixel given by `source(x, y)`
+    fn remap<F: Fn(usize, usize) -> (usize, usize) + Sync>(&self, width: usize, height: usize, source: F) -> Self {
+        let mut canvas = self.with_size(width, height);
+        if width == 0 {
+            return canvas;
+        }
+        canvas.pixels.par_chunks_mut(width)
+            .enumerate()
+            .for_each(|(y, row)| {
+                for (x, pixel) in row.iter_mut().enumerate() {
+                    let (sx, sy) = source(x, y);
+                    *pixel = self.pixels[sy * self.width + sx];
+                }
+            });
+        canvas
+    }
+}
@@ -0,0 +1,127 @@
+use rayon::prelude::*;
+
+use crate::linalg::Vec4;
+use crate::types::{
+    colortype::{InternalColorType, ColorType},
+    conversion::Luma,
+    curve::Curve,
+    histogram::Histogram
+};
+use crate::canvas::{BezierCanvas, coverage};
+
+// bins of the histograms used by the automatic adjustments
+const LEVELS: usize = 256;
+
+fn bin(c: f32, bins: usize) -> usize {
+    (c.clamp(0.0, 1.0) * (bins - 1) as f32).round() as usize
+}
+
+/*
+    Tonal adjustments on encoded straight colors, as returned by `to_vec4`, like image editors do.
+    Alpha is kept, and results are clamped to [0, 1].
+ */
+impl <InternalType: InternalColorType, ExternalType: ColorType<InternalType>> BezierCanvas<InternalType, ExternalType> {
+    pub fn histogram(&self, bins: usize) -> Histogram {
+        assert!(bins >= 2);
+        let empty = || Histogram { channels: std::array::from_fn(|_| vec![0; bins]), luma: vec![0; bins] };
+        self.pixels.par_chunks(self.width.max(1))
+            .fold(empty, |mut histogram, row| {
+                for pixel in row {
+                    let color = ExternalType::from_value(*pixel).to_vec4();
+                    for (counts, c) in histogram.channels.iter_mut().zip(color.v) {
+                        counts[bin(c, bins)] += 1;
+                    }
+                    histogram.luma[bin(Luma::Rec709.luma(color), bins)] += 1;
+                }
+                histogram
+            })
+            .reduce(empty, |mut a, b| {
+                for (counts, other) in a.channels.iter_mut().chain(std::iter::once(&mut a.luma))
+                    .zip(b.channels.iter().chain(std::iter::once(&b.luma))) {
+                    for (n, m) in counts.iter_mut().zip(other) {
+                        *n += m;
+                    }
+                }
+                a
+            })
+    }
+
+    // map [black, white] to [0, 1] on the color channels, with a gamma applied in between
+    pub fn levels(&mut self, black: f32, white: f32, gamma: f32) {
+        let black = Vec4::new(black, black, black, 0.0);
+        let white = Vec4::new(white, white, white, 1.0);
+        self.map_levels(black, white, gamma);
+    }
+
+    // stretch every color channel on its own, ignoring the darkest and brightest `clip` fraction of the pixels
+    pub fn auto_levels(&mut self, clip: f32) {
+        let histogram = self.histogram(LEVELS);
+        let mut black = Vec4::new(0.0, 0.0, 0.0, 0.0);
+        let mut white = Vec4::new(1.0, 1.0, 1.0, 1.0);
+        for i in 0..3 {
+            black.v[i] = Histogram::percentile(&histogram.channels[i], clip);
+            white.v[i] = Histogram::percentile(&histogram.channels[i], 1.0 - clip);
+        }
+        self.map_levels(black, white, 1.0);
+    }
+
+    // stretch the color channels together, keeping hues
+    pub fn auto_contrast(&mut self, clip: f32) {
+        let histogram = self.histogram(LEVELS);
+        let black = (0..3).map(|i| Histogram::percentile(&histogram.channels[i], clip)).fold(1.0, f32::min);
+        let white = (0..3).map(|i| Histogram::percentile(&histogram.channels[i], 1.0 - clip)).fold(0.0, f32::max);
+        self.levels(black, white, 1.0);
+    }
+
+    // spread the luma evenly over [0, 1] through its cumulative histogram, shifting the color channels by the change of luma
+    pub fn equalize(&mut self) {
+        let histogram = self.histogram(LEVELS);
+        let total: usize = histogram.luma.iter().sum();
+        let first = histogram.luma.iter().copied().find(|n| *n > 0).unwrap_or(0);
+        let mut seen = 0;
+        let mapping: Vec<f32> = histogram.luma.iter()
+            .map(|n| {
+                seen += n;
+                if total > first { (seen - first.min(seen)) as f32 / (total - first) as f32 } else { 0.0 }
+            })
+            .collect();
+        self.map_encoded(|color| {
+            let luma = Luma::Rec709.luma(color);
+            let shift = mapping[bin(luma, LEVELS)] - luma;
+            Vec4::new(color.x() + shift, color.y() + shift, color.z() + shift, color.w())
+        });
+    }
+
+    // map the color channels through a curve
+    pub fn curves(&mut self, curve: &Curve) {
+        self.map_encoded(|color| Vec4::new(curve.eval(color.x()), curve.eval(color.y()), curve.eval(color.z()), color.w()));
+    }
+
+    fn map_levels(&mut self, black: Vec4, white: Vec4, gamma: f32) {
+        self.map_encoded(|color| {
+            let mut ans = color;
+            for i in 0..3 {
+                let range = white.v[i] - black.v[i];
+                if range > 0.0 {
+                    ans.v[i] = ((color.v[i] - black.v[i]) / range).clamp(0.0, 1.0).powf(1.0 / gamma);
+                }
+            }
+            ans
+        });
+    }
+
+    fn map_encoded<F: Fn(Vec4) -> Vec4 + Sync>(&mut self, f: F) {
+        let space = self.working_space;
+        let mask = self.mask.as_deref();
+        self.pixels.par_iter_mut()
+            .enumerate()
+            .for_each(|(i, pixel)| {
+                let mut color = f(ExternalType::from_value(*pixel).to_vec4());
+                for c in color.v.iter_mut() {
+                    *c = c.clamp(0.0, 1.0);
+                }
+                let mapped = ExternalType::from_vec4(color).to_value();
+                *pixel = Self::mix_coverage(*pixel, mapped, space, coverage(mask, i));
+            });
+    }
+}
@@ -0,0 +1,95 @@
+use rayon::prelude::*;
+
+use crate::linalg::{Vec2, Matrix2, Matrix23, BMatrix, Det};
+use crate::types::{
+    colortype::{InternalColorType, ColorType},
+    blend::BlendMode
+};
+use crate::texture::{SampleFilter, WrapClampToEdge};
+use crate::canvas::{BezierCanvas, Raster, coverage, pixel_to_xy, xy_to_pixel};
+
+impl <InternalType: InternalColorType, ExternalType: ColorType<InternalType>> BezierCanvas<InternalType, ExternalType> {
+    /*
+        Draw another canvas, of any color type, onto this one.
+
+        `transform` is an affine map from canvas coordinates of `src` to canvas coordinates of this canvas:
+            (x, y) = transform * (s, t, 1)
+        Pixels of this canvas whose center maps inside `src` are sampled with `TextureFilter`, and blended with the alpha scaled by `opacity`.
+     */
+    pub fn draw_image<
+        SrcInternalType: InternalColorType,
+        SrcExternalType: ColorType<SrcInternalType>,
+        TextureFilter: SampleFilter<SrcInternalType, SrcExternalType>>
+        (&mut self, src: &BezierCanvas<SrcInternalType, SrcExternalType>, transform: &Matrix23, blend_mode: BlendMode, opacity: f32) {
+        self.raster().draw_image::<SrcInternalType, SrcExternalType, TextureFilter>(src, transform, blend_mode, opacity);
+    }
+
+    // draw another canvas stretched over the rectangle at `pos` of `size`
+    pub fn draw_image_rect<
+        SrcInternalType: InternalColorType,
+        SrcExternalType: ColorType<SrcInternalType>,
+        TextureFilter: SampleFilter<SrcInternalType, SrcExternalType>>
+        (&mut self, src: &BezierCanvas<SrcInternalType, SrcExternalType>, pos: &Vec2, size: &Vec2, blend_mode: BlendMode, opacity: f32) {
+        let transform = BMatrix { v: [[size.x(), 0.0, pos.x()], [0.0, size.y(), pos.y()]] };
+        self.draw_image::<SrcInternalType, SrcExternalType, TextureFilter>(src, &transform, blend_mode, opacity);
+    }
+}
+
+impl <InternalType: InternalColorType, ExternalType: ColorType<InternalType>> Raster<'_, InternalType, ExternalType> {
+    pub(super) fn draw_image<
+        SrcInternalType: InternalColorType,
+        SrcExternalType: ColorType<SrcInternalType>,
+        TextureFilter: SampleFilter<SrcInternalType, SrcExternalType>>
+        (&mut self, src: &BezierCanvas<SrcInternalType, SrcExternalType>, transform: &Matrix23, blend_mode: BlendMode, opacity: f32) {
+
+        let linear = Matrix2 { v: [[transform.v[0][0], transform.v[0][1]], [transform.v[1][0], transform.v[1][1]]] };
+        let det = linear.det();
+        if det.abs() < f32::EPSILON {
+            return;
+        }
+        let inverse = Matrix2 { v: [[linear.v[1][1], -linear.v[0][1]], [-linear.v[1][0], linear.v[0][0]]] } * (1.0 / det);
+        let offset = Vec2::new(transform.v[0][2], transform.v[1][2]);
+
+        let corners = [Vec2::new(0.0, 0.0), Vec2::new(1.0, 0.0), Vec2::new(0.0, 1.0), Vec2::new(1.0, 1.0)]
+            .map(|c| linear * c + offset);
+        let min_x = corners.iter().map(|c| c.x()).fold(f32::INFINITY, f32::min).clamp(0.0, 1.0);
+        let max_x = corners.iter().map(|c| c.x()).fold(f32::NEG_INFINITY, f32::max).clamp(0.0, 1.0);
+        let min_y = corners.iter().map(|c| c.y()).fold(f32::INFINITY, f32::min).clamp(0.0, 1.0);
+        let max_y = corners.iter().map(|c| c.y()).fold(f32::NEG_INFINITY, f32::max).clamp(0.0, 1.0);
+        let frame = self.frame;
+        let x_0 = xy_to_pixel(min_x, frame.width);
+        let x_1 = xy_to_pixel(max_x, frame.width);
+        let y_0 = xy_to_pixel(min_y, frame.height);
+        let y_1 = xy_to_pixel(max_y, frame.height);
+        let Some((x_0, x_1, y_0, y_1)) = self.clip_bounds(x_0, x_1, y_0, y_1) else {
+            return;
+        };
+
+        // canvas coordinates of src to texture coordinates, see the comments on texture filtering
+        let to_uv = |st: f32, max: usize| if max > 1 { (st * max as f32 - 0.5) / (max - 1) as f32 } else { 0.0 };
+
+        let space = self.working_space;
+        let mask = self.mask;
+        let stride = self.stride;
+        self.rows_mut()
+            .skip(y_0)
+            .take(y_1 + 1 - y_0)
+            .enumerate()
+            .for_each(|(i, chunk)| {
+                let y = i + y_0;
+                for (x, pixel) in chunk.iter_mut().enumerate().skip(x_0).take(x_1 + 1 - x_0) {
+                    let coord = Vec2::new(pixel_to_xy(frame.x + x, frame.width), pixel_to_xy(frame.y + y, frame.height));
+                    let st = inverse * (coord - offset);
+                    if st.x() < 0.0 || st.x() >= 1.0 || st.y() < 0.0 || st.y() >= 1.0 {
+                        continue;
+                    }
+                    let uv = Vec2::new(to_uv(st.x(), src.width), to_uv(st.y(), src.height));
+                    let mut fg = space.decode(src.sample::<TextureFilter, WrapClampToEdge, WrapClampToEdge>(&uv));
+                    fg.v[3] *= opacity;
+                    let bg = space.to_vec4(&ExternalType::from_value(*pixel));
+                    let blended = space.from_vec4::<InternalType, ExternalType>(blend_mode.blend_vec4(bg, fg)).to_value();
+                    *pixel = BezierCanvas::<InternalType, ExternalType>::mix_coverage(*pixel, blended, space, coverage(mask, y * stride + x));
+                }
+            });
+    }
+}
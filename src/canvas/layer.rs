@@ -0,0 +1,50 @@
+use num::Zero;
+use rayon::prelude::*;
+
+use crate::types::{
+    colortype::{InternalColorType, ColorType},
+    blend::BlendMode
+};
+use crate::canvas::BezierCanvas;
+
+// canvas content under an offscreen layer, and how to composite the layer back onto it
+pub(crate) struct Layer<InternalType: InternalColorType> {
+    backdrop: Vec<InternalType>,
+    opacity: f32,
+    blend_mode: BlendMode
+}
+
+impl <InternalType: InternalColorType, ExternalType: ColorType<InternalType>> BezierCanvas<InternalType, ExternalType> {
+    /*
+        Redirect subsequent drawing into an offscreen buffer, until the matching `pop_layer`.
+
+        The buffer starts from the zero value, so the color type needs alpha for it to be transparent;
+        on types without alpha, such as RGB, the layer would cover the whole canvas, and this panics instead.
+        Layers can be nested.
+     */
+    pub fn push_layer(&mut self, opacity: f32, blend_mode: BlendMode) {
+        assert!(ExternalType::from_value(Zero::zero()).to_vec4().w() == 0.0, "layers need a color type with alpha");
+        let backdrop = std::mem::replace(&mut self.pixels, vec![Zero::zero(); self.width * self.height]);
+        self.layers.push(Layer { backdrop, opacity, blend_mode });
+    }
+
+    /*
+        Composite the innermost layer back onto what is under it as a single image,
+        with the alpha of the layer scaled by its opacity.
+     */
+    pub fn pop_layer(&mut self) {
+        let layer = self.layers.pop().expect("pop_layer without a matching push_layer");
+        let group = std::mem::replace(&mut self.pixels, layer.backdrop);
+        let space = self.working_space;
+        let opacity = layer.opacity;
+        let blend_mode = layer.blend_mode;
+        self.pixels.par_iter_mut()
+            .zip(group.par_iter())
+            .for_each(|(bg, fg)| {
+                let mut fg_vec = space.to_vec4(&ExternalType::from_value(*fg));
+                fg_vec.v[3] *= opacity;
+                let bg_vec = space.to_vec4(&ExternalType::from_value(*bg));
+                *bg = space.from_vec4::<InternalType, ExternalType>(blend_mode.blend_vec4(bg_vec, fg_vec)).to_value();
+            });
+    }
+}
@@ -0,0 +1,39 @@
+use rayon::prelude::*;
+
+use crate::types::{
+    colortype::{InternalColorType, ColorType},
+    mask::MaskMode
+};
+use crate::canvas::BezierCanvas;
+
+impl <InternalType: InternalColorType, ExternalType: ColorType<InternalType>> BezierCanvas<InternalType, ExternalType> {
+    /*
+        Draw subsequent fills, strokes and shaded triangles through a mask, until `clear_mask`.
+
+        Any canvas can be a mask, e.g. an `A` canvas with `MaskMode::Alpha` or an `R` canvas with `MaskMode::Luminance`.
+        A mask of a different size is stretched over this canvas, taking the nearest mask pixel, so it must not be empty.
+     */
+    pub fn set_mask<MaskInternalType: InternalColorType, MaskExternalType: ColorType<MaskInternalType>>(&mut self, mask: &BezierCanvas<MaskInternalType, MaskExternalType>, mode: MaskMode) {
+        assert!(mask.width > 0 && mask.height > 0, "empty mask");
+        let mut coverage = vec![0f32; self.width * self.height];
+        let (width, height) = (self.width, self.height);
+        if width == 0 {
+            self.mask = Some(coverage);
+            return;
+        }
+        coverage.par_chunks_mut(width)
+            .enumerate()
+            .for_each(|(y, row)| {
+                let mask_y = (y * mask.height / height).min(mask.height - 1);
+                for (x, value) in row.iter_mut().enumerate() {
+                    let mask_x = (x * mask.width / width).min(mask.width - 1);
+                    *value = mode.coverage(mask.get_pixel(mask_x, mask_y).to_vec4()).clamp(0.0, 1.0);
+                }
+            });
+        self.mask = Some(coverage);
+    }
+
+    pub fn clear_mask(&mut self) {
+        self.mask = None;
+    }
+}
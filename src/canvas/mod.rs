@@ -1,6 +1,24 @@
+mod blur;
+mod colormatrix;
+mod compare;
+mod conversion;
 mod convert;
+mod convolve;
+mod dither;
+mod fill;
+mod geometry;
+mod histogram;
+mod image;
+mod layer;
+mod mask;
+mod morphology;
+mod palette;
+mod resize;
 mod shade;
 mod texture;
+mod tiled;
+mod tonemap;
+mod view;
 
 use std::marker::PhantomData;
 use num::traits::Zero;
@@ -8,15 +26,57 @@ use rayon::slice::ParallelSliceMut;
 use rayon::prelude::*;
 
 use crate::linalg::Vec2;
+use layer::Layer;
+pub use view::BezierCanvasViewMut;
+pub use compare::assert_canvas_matches;
+pub use tiled::TiledCanvas;
 use crate::types::{
     colortype::{ColorType, InternalColorType},
-    blend::BlendMode
+    blend::BlendMode,
+    dither::Dither,
+    gamma::WorkingSpace
 };
 
 pub struct BezierCanvas<InternalType: InternalColorType, ExternalType: ColorType<InternalType>> {
     pub width: usize,
     pub height: usize,
+    pub working_space: WorkingSpace,
+    // dithering of fragments shaded with `FragOut::from_vec4`
+    pub dither: Dither,
     pixels: Vec<InternalType>,
+    layers: Vec<Layer<InternalType>>,
+    // coverage of every pixel, set by `set_mask`
+    mask: Option<Vec<f32>>,
+    // part of a larger canvas the pixels are, as for the tiles of a `TiledCanvas`
+    frame: Option<Frame>,
+    external_type: PhantomData<ExternalType>
+}
+
+/*
+    The pixels of a canvas are the `width` by `height` pixels at (x, y) of a canvas of the frame size.
+    Drawing is rasterized in pixels of the frame, so a part renders exactly the pixels of the whole canvas.
+ */
+#[derive(Clone, Copy)]
+struct Frame {
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize
+}
+
+/*
+    Pixels drawing writes to: the whole buffer of a canvas, or a rect of it for a view, with rows `stride` pixels apart.
+    `pixels`, and `mask` if any, start at the top-left pixel of the rect.
+ */
+struct Raster<'a, InternalType: InternalColorType, ExternalType: ColorType<InternalType>> {
+    pixels: &'a mut [InternalType],
+    mask: Option<&'a [f32]>,
+    width: usize,
+    height: usize,
+    stride: usize,
+    frame: Frame,
+    working_space: WorkingSpace,
+    dither: Dither,
     external_type: PhantomData<ExternalType>
 }
 const MAX_PASCAL: usize = 10;
@@ -36,9 +96,14 @@ const C: [[usize; MAX_PASCAL]; MAX_PASCAL] = [
 impl <InternalType: InternalColorType, ExternalType: ColorType<InternalType>> BezierCanvas<InternalType, ExternalType> {
     pub fn new(width: usize, height: usize) -> Self {
         BezierCanvas {
-            width: width,
-            height: height,
+            width,
+            height,
+            working_space: WorkingSpace::Gamma,
+            dither: Dither::None,
             pixels: vec![Zero::zero(); width * height],
+            layers: Vec::new(),
+            mask: None,
+            frame: None,
             external_type: PhantomData
         }
     }
@@ -47,64 +112,168 @@ impl <InternalType: InternalColorType, ExternalType: ColorType<InternalType>> Be
         ExternalType::from_value(self.pixels[y * self.width + x])
     }
 
-    fn set_pixel(&mut self, x: usize, y: usize, pixel: &ExternalType, blend_mode: BlendMode) {
-        self.pixels[y * self.width + x] = blend_mode.blend(self.pixels[y * self.width + x], pixel);
+    // the internal buffer, row by row, e.g. to hand packed pixels to display code
+    pub fn raw_pixels(&self) -> &[InternalType] {
+        &self.pixels
     }
 
-    fn par_set_pixel(pixel: &mut InternalType, color: &ExternalType, blend_mode: BlendMode) {
-        *pixel = blend_mode.blend(*pixel, color);
+    pub fn raw_pixels_mut(&mut self) -> &mut [InternalType] {
+        &mut self.pixels
     }
 
-    /*
-        a little different when treated as texture and canvas,
+    // blend, and then mix with the previous color by coverage
+    fn par_set_pixel(pixel: &mut InternalType, color: &ExternalType, blend_mode: BlendMode, space: WorkingSpace, coverage: f32) {
+        if coverage <= 0.0 {
+            return;
+        }
+        let blended = blend_mode.blend_in(*pixel, color, space);
+        *pixel = Self::mix_coverage(*pixel, blended, space, coverage);
+    }
+
+    fn mix_coverage(old: InternalType, new: InternalType, space: WorkingSpace, coverage: f32) -> InternalType {
+        if coverage >= 1.0 {
+            return new;
+        }
+        let bg = space.to_premul_vec4(&ExternalType::from_value(old));
+        let fg = space.to_premul_vec4(&ExternalType::from_value(new));
+        space.from_premul_vec4::<InternalType, ExternalType>(bg + (fg - bg) * coverage).to_value()
+    }
 
-        when treated as a texture (indexed by uv), (0.0, 0.0) is at the top-left corner color point, and (1.0, 1.0) is at the bottom-right corner color points.
-            the texture is considered as a 2D grid of color points, each point is a pixel; the color is sampled from an interpolation of nearby color points
+    fn frame(&self) -> Frame {
+        self.frame.unwrap_or(Frame { x: 0, y: 0, width: self.width, height: self.height })
+    }
+
+    fn raster(&mut self) -> Raster<'_, InternalType, ExternalType> {
+        Raster {
+            frame: self.frame(),
+            pixels: &mut self.pixels,
+            mask: self.mask.as_deref(),
+            width: self.width,
+            height: self.height,
+            stride: self.width,
+            working_space: self.working_space,
+            dither: self.dither,
+            external_type: PhantomData
+        }
+    }
+
+    pub fn fill_rect(&mut self, pos: &Vec2, size: &Vec2, color: &ExternalType, blend_mode: BlendMode) {
+        self.raster().fill_rect(pos, size, color, blend_mode);
+    }
+
+    pub fn fill_oval(&mut self, pos: &Vec2, size: &Vec2, color: &ExternalType, blend_mode: BlendMode) {
+        self.raster().fill_oval(pos, size, color, blend_mode);
+    }
 
-        when treated as a canvas, (0.0, 0.0) is at the top-left corner of the top-left corner pixel, and (1.0, 1.0) is at the bottom-right bottom-right corner pixel.
-            the canvas is considered as a continuous field with square pixels, whose edge is on integral xy points. if a point we need to fill is within a pixel, we fill this pixel.
+    pub fn fill_circle(&mut self, pos: &Vec2, radius: f32, color: &ExternalType, blend_mode: BlendMode) {
+        self.fill_oval(pos, &Vec2 {v: [radius, radius]}, color, blend_mode);
+    }
+
+    pub fn stroke_line(&mut self, pos0: &Vec2, pos1: &Vec2, color: &ExternalType, blend_mode: BlendMode) {
+        self.raster().stroke_line(pos0, pos1, color, blend_mode);
+    }
+
+    pub fn stroke_bezier<const N: usize>(&mut self, poses: &[Vec2], color: &ExternalType, stops: usize, blend_mode: BlendMode) {
+        self.raster().stroke_bezier::<N>(poses, color, stops, blend_mode);
+    }
+
+    /**
+        Fill the shape, defined by contours.
+
+        A contour is a list of `Vec2`, defining the points on the contour, interpolated linearly.
+        The shape is defined by **xor**ing all the shapes defined in contours, so orientation does not matter. The 0th point in contour is considered the closing point on the last edge, so it is not needed to include the point twice.
+        
+        However, for compatibility, clockwise outer contour and counterclockwise inner contour is still recommended.
      */
-    fn pixel_to_xy(pix: usize, max: usize) -> f32 {
-        (pix as f32 + 0.5) / (max as f32)
+    #[allow(clippy::ptr_arg)] // the public signature predates views, which take slices
+    pub fn fill_shape(&mut self, contours: &Vec<Vec<Vec2>>, color: &ExternalType, blend_mode: BlendMode) {
+        self.raster().fill_shape(contours, color, blend_mode);
     }
+}
 
+impl <InternalType: InternalColorType, ExternalType: ColorType<InternalType>> Raster<'_, InternalType, ExternalType> {
+    // rows of `width` pixels
+    fn rows_mut(&mut self) -> impl IndexedParallelIterator<Item = &mut [InternalType]> {
+        let width = self.width;
+        self.pixels.par_chunks_mut(self.stride.max(1)).map(move |row| &mut row[..width])
+    }
 
-    fn xy_to_pixel(xy: f32, max: usize) -> usize {
-        (xy * (max as f32) - 0.5).round() as usize
+    // (x, y) in pixels of the frame
+    fn set_pixel(&mut self, x: usize, y: usize, pixel: &ExternalType, blend_mode: BlendMode) {
+        let Some((x, _, y, _)) = self.clip_bounds(x, x, y, y) else {
+            return;
+        };
+        let coverage = coverage(self.mask, y * self.stride + x);
+        BezierCanvas::par_set_pixel(&mut self.pixels[y * self.stride + x], pixel, blend_mode, self.working_space, coverage);
     }
 
-    pub fn fill_rect(&mut self, pos: &Vec2, size: &Vec2, color: &ExternalType, blend_mode: BlendMode) {
-        let x_0 = Self::xy_to_pixel(pos.x().clamp(0.0, 1.0), self.width);
-        let x_1: usize = Self::xy_to_pixel((pos.x() + size.x()).clamp(0.0, 1.0), self.width);
-        let y_0 = Self::xy_to_pixel(pos.y().clamp(0.0, 1.0), self.height);
-        let y_1: usize = Self::xy_to_pixel((pos.y() + size.y()).clamp(0.0, 1.0), self.height);
-        self.pixels.par_chunks_mut(self.width)
+    /*
+        Inclusive pixel bounds of a drawing, in pixels of the frame,
+        to pixels of the raster, restricted to it; None when nothing is left to draw.
+     */
+    fn clip_bounds(&self, x_0: usize, x_1: usize, y_0: usize, y_1: usize) -> Option<(usize, usize, usize, usize)> {
+        if self.width == 0 || self.height == 0 {
+            return None;
+        }
+        let frame = self.frame;
+        let (x_0, x_1) = (x_0.max(frame.x), x_1.min(frame.x + self.width - 1));
+        let (y_0, y_1) = (y_0.max(frame.y), y_1.min(frame.y + self.height - 1));
+        if x_0 > x_1 || y_0 > y_1 {
+            return None;
+        }
+        Some((x_0 - frame.x, x_1 - frame.x, y_0 - frame.y, y_1 - frame.y))
+    }
+
+    fn fill_rect(&mut self, pos: &Vec2, size: &Vec2, color: &ExternalType, blend_mode: BlendMode) {
+        let frame = self.frame;
+        let x_0 = xy_to_pixel(pos.x().clamp(0.0, 1.0), frame.width);
+        let x_1: usize = xy_to_pixel((pos.x() + size.x()).clamp(0.0, 1.0), frame.width);
+        let y_0 = xy_to_pixel(pos.y().clamp(0.0, 1.0), frame.height);
+        let y_1: usize = xy_to_pixel((pos.y() + size.y()).clamp(0.0, 1.0), frame.height);
+        let Some((x_0, x_1, y_0, y_1)) = self.clip_bounds(x_0, x_1, y_0, y_1) else {
+            return;
+        };
+        let space = self.working_space;
+        let mask = self.mask;
+        let stride = self.stride;
+        self.rows_mut()
             .skip(y_0)
             .take(y_1 + 1 - y_0)
-            .for_each(| chunk| {
+            .enumerate()
+            .for_each(|(i, chunk)| {
+                let y = i + y_0;
                 chunk.par_iter_mut()
                     .skip(x_0)
                     .take(x_1 + 1 - x_0)
-                    .for_each(|pixel| {
-                        BezierCanvas::par_set_pixel(pixel, color, blend_mode);
+                    .enumerate()
+                    .for_each(|(j, pixel)| {
+                        let x = j + x_0;
+                        BezierCanvas::par_set_pixel(pixel, color, blend_mode, space, coverage(mask, y * stride + x));
                     })
             });
     }
-    pub fn fill_oval(&mut self, pos: &Vec2, size: &Vec2, color: &ExternalType, blend_mode: BlendMode) {
-        let x_0 = Self::xy_to_pixel((pos.x() - size.x()).clamp(0.0, 1.0), self.width);
-        let x_1: usize = Self::xy_to_pixel((pos.x() + size.x()).clamp(0.0, 1.0), self.width);
-        let y_0 = Self::xy_to_pixel((pos.y() - size.y()).clamp(0.0, 1.0), self.height);
-        let y_1: usize = Self::xy_to_pixel((pos.y() + size.y()).clamp(0.0, 1.0), self.height);
-        
+    fn fill_oval(&mut self, pos: &Vec2, size: &Vec2, color: &ExternalType, blend_mode: BlendMode) {
+        let frame = self.frame;
+        let x_0 = xy_to_pixel((pos.x() - size.x()).clamp(0.0, 1.0), frame.width);
+        let x_1: usize = xy_to_pixel((pos.x() + size.x()).clamp(0.0, 1.0), frame.width);
+        let y_0 = xy_to_pixel((pos.y() - size.y()).clamp(0.0, 1.0), frame.height);
+        let y_1: usize = xy_to_pixel((pos.y() + size.y()).clamp(0.0, 1.0), frame.height);
+        let Some((x_0, x_1, y_0, y_1)) = self.clip_bounds(x_0, x_1, y_0, y_1) else {
+            return;
+        };
+
         let w2 = size.x() * size.x();
         let h2 = size.y() * size.y();
-        self.pixels.par_chunks_mut(self.width)
+        let space = self.working_space;
+        let mask = self.mask;
+        let stride = self.stride;
+        self.rows_mut()
             .skip(y_0)
             .take(y_1 + 1 - y_0)
             .enumerate()
             .for_each(|(i, chunk)| {
                 let y = i + y_0;
-                let rel_y = Self::pixel_to_xy(y, self.height) - pos.y();
+                let rel_y = pixel_to_xy(frame.y + y, frame.height) - pos.y();
                 let y2 = rel_y * rel_y;
                 chunk.par_iter_mut()
                     .skip(x_0)
@@ -112,51 +281,46 @@ impl <InternalType: InternalColorType, ExternalType: ColorType<InternalType>> Be
                     .enumerate()
                     .for_each(|(j, pixel)| {
                         let x = j + x_0;
-                        let rel_x = Self::pixel_to_xy(x, self.width) - pos.x();
+                        let rel_x = pixel_to_xy(frame.x + x, frame.width) - pos.x();
                         let x2 = rel_x * rel_x;
                         if x2 / w2 + y2 / h2 <= 1f32 {
-                            BezierCanvas::par_set_pixel(pixel, color, blend_mode);
+                            BezierCanvas::par_set_pixel(pixel, color, blend_mode, space, coverage(mask, y * stride + x));
                         }
                     })
             });
     }
 
-    pub fn fill_circle(&mut self, pos: &Vec2, radius: f32, color: &ExternalType, blend_mode: BlendMode) {
-        self.fill_oval(pos, &Vec2 {v: [radius, radius]}, color, blend_mode);
-    }
-
     fn stroke_line_gentle(&mut self, pos0: &Vec2, pos1: &Vec2, color: &ExternalType, blend_mode: BlendMode) {
         // with abs(slope) < 1
-        let x_0 = Self::xy_to_pixel(pos0.x().clamp(0.0, pos1.x()), self.width);
-        let mut x_1: usize = Self::xy_to_pixel(pos0.x().clamp(pos1.x(), 1.0), self.width);
-        if x_1 >= self.width {
-            x_1 = self.height - 1;
-        }
+        // endpoints may be outside the canvas
+        let frame = self.frame;
+        let Some((x_0, x_1)) = xy_to_pixel_range(pos0.x().min(pos1.x()), pos0.x().max(pos1.x()), frame.x, self.width, frame.width) else {
+            return;
+        };
         for x in x_0..=x_1 {
-            let y = (pos1.y() - pos0.y()) / (pos1.x() - pos0.x()) * Self::pixel_to_xy(x, self.width) +
+            let y = (pos1.y() - pos0.y()) / (pos1.x() - pos0.x()) * pixel_to_xy(x, frame.width) +
              (pos0.y() - (pos1.y() - pos0.y()) / (pos1.x() - pos0.x()) * pos0.x());
-            if y >= 0.0f32 && y < 1.0f32 {
-                self.set_pixel(x, Self::xy_to_pixel(y, self.height), color, blend_mode);
+            if (0.0f32..1.0f32).contains(&y) {
+                self.set_pixel(x, xy_to_pixel(y, frame.height), color, blend_mode);
             }
         }
     }
     fn stroke_line_steep(&mut self, pos0: &Vec2, pos1: &Vec2, color: &ExternalType, blend_mode: BlendMode) {
         // with abs(slope) < 1
 
-        let y_0 = Self::xy_to_pixel(pos0.y().clamp(0.0, pos1.y()), self.height);
-        let mut y_1: usize = Self::xy_to_pixel(pos0.y().clamp(pos1.y(), 1.0), self.height);
-        if y_1 >= self.height {
-            y_1 = self.height - 1;
-        }
+        let frame = self.frame;
+        let Some((y_0, y_1)) = xy_to_pixel_range(pos0.y().min(pos1.y()), pos0.y().max(pos1.y()), frame.y, self.height, frame.height) else {
+            return;
+        };
         for y in y_0..=y_1 {
-            let x = (pos1.x() - pos0.x()) / (pos1.y() - pos0.y()) * Self::pixel_to_xy(y, self.height) +
+            let x = (pos1.x() - pos0.x()) / (pos1.y() - pos0.y()) * pixel_to_xy(y, frame.height) +
              (pos0.x() - (pos1.x() - pos0.x()) / (pos1.y() - pos0.y()) * pos0.y());
-            if x >= 0.0f32 && x < 1.0f32 {
-                self.set_pixel(Self::xy_to_pixel(x, self.width), y, color, blend_mode);
+            if (0.0f32..1.0f32).contains(&x) {
+                self.set_pixel(xy_to_pixel(x, frame.width), y, color, blend_mode);
             }
         }
     }
-    pub fn stroke_line(&mut self, pos0: &Vec2, pos1: &Vec2, color: &ExternalType, blend_mode: BlendMode) {
+    fn stroke_line(&mut self, pos0: &Vec2, pos1: &Vec2, color: &ExternalType, blend_mode: BlendMode) {
         let dx = (pos1.x() - pos0.x()).abs();
         let dy = (pos1.y() - pos0.y()).abs();
         if dx > dy {
@@ -166,7 +330,7 @@ impl <InternalType: InternalColorType, ExternalType: ColorType<InternalType>> Be
             self.stroke_line_steep(pos0, pos1, color, blend_mode);
         }
     }
-    pub fn stroke_bezier<const N: usize>(&mut self, poses: &[Vec2], color: &ExternalType, stops: usize, blend_mode: BlendMode) {
+    fn stroke_bezier<const N: usize>(&mut self, poses: &[Vec2], color: &ExternalType, stops: usize, blend_mode: BlendMode) {
         // draw (N - 1)-th order bezier curve
         assert!(N < MAX_PASCAL);
         let mut prev_ans: Option<Vec2> = None;
@@ -191,37 +355,26 @@ impl <InternalType: InternalColorType, ExternalType: ColorType<InternalType>> Be
             prev_ans = Some(ans);
         }
     }
-    /**
-        Fill the shape, defined by contours.
-
-        A contour is a list of `Vec2`, defining the points on the contour, interpolated linearly.
-        The shape is defined by **xor**ing all the shapes defined in contours, so orientation does not matter. The 0th point in contour is considered the closing point on the last edge, so it is not needed to include the point twice.
-        
-        However, for compatibility, clockwise outer contour and counterclockwise inner contour is still recommended.
-     */
-    pub fn fill_shape(&mut self, contours: &Vec<Vec<Vec2>>, color: &ExternalType, blend_mode: BlendMode) {
-        let mut min_x = 1f32;
-        let mut min_y = 1f32;
-        let mut max_x = 0f32;
-        let mut max_y = 0f32;
+    fn fill_shape(&mut self, contours: &[Vec<Vec2>], color: &ExternalType, blend_mode: BlendMode) {
+        let mut min_x = f32::INFINITY;
+        let mut min_y = f32::INFINITY;
+        let mut max_x = f32::NEG_INFINITY;
+        let mut max_y = f32::NEG_INFINITY;
         for contour in contours {
             for pnt in contour {
-                if pnt.x() < min_x {
-                    min_x = pnt.x();
-                } else if pnt.x() > max_x {
-                    max_x = pnt.x();
-                }
-                if pnt.y() < min_y {
-                    min_y = pnt.y();
-                } else if pnt.y() > max_y {
-                    max_y = pnt.y();
-                }
+                min_x = min_x.min(pnt.x());
+                max_x = max_x.max(pnt.x());
+                min_y = min_y.min(pnt.y());
+                max_y = max_y.max(pnt.y());
             }
         }
-        let x_0 = Self::xy_to_pixel(min_x, self.width);
-        let x_1 = Self::xy_to_pixel(max_x, self.width);
-        let y_0 = Self::xy_to_pixel(min_y, self.height);
-        let y_1 = Self::xy_to_pixel(max_y, self.height);
+        let frame = self.frame;
+        let Some((x_0, x_1)) = xy_to_pixel_range(min_x, max_x, frame.x, self.width, frame.width) else {
+            return;
+        };
+        let Some((y_0, y_1)) = xy_to_pixel_range(min_y, max_y, frame.y, self.height, frame.height) else {
+            return;
+        };
         let mut pass_time = vec![0i8; (x_1 - x_0 + 1) * (y_1 - y_0 + 1)]; // i8 should be enough, only take the lowest bit
         for contour in contours {
             let contour_len = contour.len();
@@ -229,7 +382,7 @@ impl <InternalType: InternalColorType, ExternalType: ColorType<InternalType>> Be
             .enumerate()
             .for_each(|(i, times)| {
                 let y = i + y_0;
-                let yf = Self::pixel_to_xy(y, self.height);
+                let yf = pixel_to_xy(y, frame.height);
                 let mut intersections: Vec<f32> = Vec::new();
                 for k in 0..contour_len {
                     let p0 = contour[k];
@@ -244,7 +397,7 @@ impl <InternalType: InternalColorType, ExternalType: ColorType<InternalType>> Be
                         continue;
                     }
                     let t = (yf - p0.y()) / delta.y();
-                    if t < 0f32 || t >= 1f32 {
+                    if !(0f32..1f32).contains(&t) {
                         continue;
                     }
                     let x = p0.x() + t * delta.x();
@@ -256,8 +409,9 @@ impl <InternalType: InternalColorType, ExternalType: ColorType<InternalType>> Be
                 // there should be even number of intersections;
                 assert!((intersect_len & 1) == 0);
                 for k in (0..intersect_len).step_by(2) {
-                    let x_start = Self::xy_to_pixel(intersections[k], self.width);
-                    let x_end= Self::xy_to_pixel(intersections[k + 1], self.width);
+                    let Some((x_start, x_end)) = xy_to_pixel_range(intersections[k], intersections[k + 1], x_0, x_1 + 1 - x_0, frame.width) else {
+                        continue;
+                    };
                     for x in x_start..=x_end {
                         let j = x - x_0;
                         times[j] ^= 1;
@@ -277,3 +431,33 @@ impl <InternalType: InternalColorType, ExternalType: ColorType<InternalType>> Be
     }
 }
 
+/*
+    a little different when treated as texture and canvas,
+
+    when treated as a texture (indexed by uv), (0.0, 0.0) is at the top-left corner color point, and (1.0, 1.0) is at the bottom-right corner color points.
+        the texture is considered as a 2D grid of color points, each point is a pixel; the color is sampled from an interpolation of nearby color points
+
+    when treated as a canvas, (0.0, 0.0) is at the top-left corner of the top-left corner pixel, and (1.0, 1.0) is at the bottom-right bottom-right corner pixel.
+        the canvas is considered as a continuous field with square pixels, whose edge is on integral xy points. if a point we need to fill is within a pixel, we fill this pixel.
+ */
+fn pixel_to_xy(pix: usize, max: usize) -> f32 {
+    (pix as f32 + 0.5) / (max as f32)
+}
+
+fn xy_to_pixel(xy: f32, max: usize) -> usize {
+    (xy * (max as f32) - 0.5).round() as usize
+}
+
+// pixels nearest to the interval [lo, hi] among the `len` pixels from `start` of `max`, None when there are none
+fn xy_to_pixel_range(lo: f32, hi: f32, start: usize, len: usize, max: usize) -> Option<(usize, usize)> {
+    let lo = (lo * (max as f32) - 0.5).round();
+    let hi = (hi * (max as f32) - 0.5).round();
+    if len == 0 || hi < start as f32 || lo > (start + len - 1) as f32 || lo > hi {
+        return None;
+    }
+    Some((lo.max(start as f32) as usize, (hi as usize).min(start + len - 1)))
+}
+
+fn coverage(mask: Option<&[f32]>, index: usize) -> f32 {
+    mask.map_or(1.0, |mask| mask[index])
+}
@@ -0,0 +1,121 @@
+use std::collections::HashMap;
+
+use rayon::prelude::*;
+
+use crate::linalg::Vec4;
+use crate::types::{
+    colortype::{ColorType, PremulRGBA, RGBA},
+    palette::PaletteMethod
+};
+use crate::canvas::BezierCanvas;
+
+// colors are compared premultiplied, so all fully transparent colors are the same
+fn key(color: &RGBA) -> Vec4 {
+    color.to_premul_vec4()
+}
+
+fn distance(a: &Vec4, b: &Vec4) -> f32 {
+    let d = *a - *b;
+    d.star(&d).v.iter().sum()
+}
+
+// premultiplied mean of weighted colors
+fn mean(colors: &[(Vec4, usize)]) -> Vec4 {
+    let mut sum = Vec4::new(0.0, 0.0, 0.0, 0.0);
+    let mut count = 0;
+    for (color, n) in colors {
+        sum = sum + *color * (*n as f32);
+        count += n;
+    }
+    sum * (1.0 / count.max(1) as f32)
+}
+
+impl BezierCanvas<u32, RGBA> {
+    /*
+        Reduce the colors of this canvas to a palette of at most `max_colors` colors,
+        and never more than 256, the most an indexed PNG can hold. A palette has at least one color, so `max_colors` must not be 0.
+     */
+    pub fn palette(&self, max_colors: usize, method: PaletteMethod) -> Vec<RGBA> {
+        assert!(max_colors > 0, "a palette needs at least one color");
+        let max_colors = max_colors.min(256);
+        let mut histogram: HashMap<u32, usize> = HashMap::new();
+        for pixel in &self.pixels {
+            let premul = PremulRGBA::from_vec4(RGBA::from_value(*pixel).to_vec4());
+            *histogram.entry(premul.to_value()).or_insert(0) += 1;
+        }
+        let colors: Vec<(Vec4, usize)> = histogram.into_iter()
+            .map(|(premul, n)| (PremulRGBA::from_value(premul).to_premul_vec4(), n))
+            .collect();
+
+        // median cut: split the box with the widest channel range at the median of that channel
+        let mut boxes = vec![colors];
+        while boxes.len() < max_colors {
+            let widest = boxes.iter()
+                .enumerate()
+                .filter(|(_, b)| b.len() > 1)
+                .map(|(i, b)| {
+                    let (channel, range) = (0..4).map(|k| {
+                        let min = b.iter().map(|(c, _)| c.v[k]).fold(f32::INFINITY, f32::min);
+                        let max = b.iter().map(|(c, _)| c.v[k]).fold(f32::NEG_INFINITY, f32::max);
+                        (k, max - min)
+                    }).max_by(|a, b| a.1.partial_cmp(&b.1).unwrap()).unwrap();
+                    (i, channel, range)
+                })
+                .max_by(|a, b| a.2.partial_cmp(&b.2).unwrap());
+            let Some((i, channel, _)) = widest else {
+                break;
+            };
+            let mut split = boxes.swap_remove(i);
+            split.sort_by(|a, b| a.0.v[channel].partial_cmp(&b.0.v[channel]).unwrap());
+            let total: usize = split.iter().map(|(_, n)| n).sum();
+            let mut seen = 0;
+            let mut median = 1;
+            for (k, (_, n)) in split.iter().enumerate() {
+                seen += n;
+                if seen * 2 >= total {
+                    median = (k + 1).clamp(1, split.len() - 1);
+                    break;
+                }
+            }
+            let upper = split.split_off(median);
+            boxes.push(split);
+            boxes.push(upper);
+        }
+        let mut centers: Vec<Vec4> = boxes.iter().map(|b| mean(b)).collect();
+
+        if let PaletteMethod::KMeans(iterations) = method {
+            let colors: Vec<(Vec4, usize)> = boxes.concat();
+            for _ in 0..iterations {
+                let mut clusters: Vec<Vec<(Vec4, usize)>> = vec![Vec::new(); centers.len()];
+                for (color, n) in &colors {
+                    clusters[nearest(&centers, color)].push((*color, *n));
+                }
+                for (center, cluster) in centers.iter_mut().zip(clusters) {
+                    if !cluster.is_empty() {
+                        *center = mean(&cluster);
+                    }
+                }
+            }
+        }
+        centers.iter().map(|c| RGBA::from_premul_vec4(*c)).collect()
+    }
+
+    // index of the nearest palette color of every pixel
+    pub fn map_to_palette(&self, palette: &[RGBA]) -> Vec<u8> {
+        assert!(!palette.is_empty(), "empty palette");
+        assert!(palette.len() <= 256, "more than 256 palette colors");
+        let centers: Vec<Vec4> = palette.iter().map(key).collect();
+        self.pixels.par_iter()
+            .map(|pixel| nearest(&centers, &key(&RGBA::from_value(*pixel))) as u8)
+            .collect()
+    }
+}
+
+fn nearest(centers: &[Vec4], color: &Vec4) -> usize {
+    centers.iter()
+        .enumerate()
+        .map(|(i, c)| (i, distance(c, color)))
+        .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap())
+        .unwrap()
+        .0
+}
@@ -0,0 +1,78 @@
+use num::Zero;
+use rayon::prelude::*;
+
+use crate::linalg::Vec4;
+use crate::types::{
+    colortype::{InternalColorType, ColorType, unpremultiply},
+    resize::ResizeKernel
+};
+use crate::canvas::BezierCanvas;
+
+// source pixels and normalized weights contributing to each destination pixel along one axis
+fn contributions(src: usize, dst: usize, kernel: ResizeKernel) -> Vec<Vec<(usize, f32)>> {
+    let scale = src as f32 / dst as f32;
+    // when downscaling, the kernel is stretched to cover the whole source area of a destination pixel
+    let stretch = scale.max(1.0);
+    let support = kernel.support() * stretch;
+    (0..dst).map(|i| {
+        let center = (i as f32 + 0.5) * scale - 0.5;
+        let start = (center - support).floor() as isize;
+        let end = (center + support).ceil() as isize;
+        let mut weights: Vec<(usize, f32)> = (start..=end)
+            .map(|j| (j.clamp(0, src as isize - 1) as usize, kernel.weight((j as f32 - center) / stretch)))
+            .filter(|(_, w)| *w != 0.0)
+            .collect();
+        let sum: f32 = weights.iter().map(|(_, w)| w).sum();
+        for (_, w) in weights.iter_mut() {
+            *w /= sum;
+        }
+        weights
+    }).collect()
+}
+
+impl <InternalType: InternalColorType, ExternalType: ColorType<InternalType>> BezierCanvas<InternalType, ExternalType> {
+    /*
+        Resample into a new canvas of the given size, with separable horizontal and vertical passes.
+
+        Colors are filtered premultiplied in the working space of this canvas.
+     */
+    pub fn resize(&self, width: usize, height: usize, kernel: ResizeKernel) -> Self {
+        let space = self.working_space;
+        let mut canvas = BezierCanvas::new(width, height);
+        canvas.working_space = space;
+        // nothing to resample into, or nothing to resample, which leaves the canvas blank
+        if width == 0 || height == 0 || self.width == 0 || self.height == 0 {
+            return canvas;
+        }
+        let src: Vec<Vec4> = self.pixels.par_iter()
+            .map(|pixel| space.to_premul_vec4(&ExternalType::from_value(*pixel)))
+            .collect();
+
+        let horizontal = contributions(self.width, width, kernel);
+        let mut intermediate = vec![Vec4::zero(); width * self.height];
+        intermediate.par_chunks_mut(width)
+            .zip(src.par_chunks(self.width))
+            .for_each(|(dst_row, src_row)| {
+                for (dst, weights) in dst_row.iter_mut().zip(&horizontal) {
+                    *dst = weights.iter().fold(Vec4::zero(), |acc, (j, w)| acc + src_row[*j] * *w);
+                }
+            });
+
+        let vertical = contributions(self.height, height, kernel);
+        canvas.pixels.par_chunks_mut(width)
+            .zip(vertical.par_iter())
+            .for_each(|(dst_row, weights)| {
+                for (x, dst) in dst_row.iter_mut().enumerate() {
+                    let color = weights.iter().fold(Vec4::zero(), |acc, (j, w)| acc + intermediate[j * width + x] * *w);
+                    // ringing kernels may overshoot, keep alpha valid and colors non-negative (but HDR)
+                    let mut color = unpremultiply(color);
+                    for c in color.v.iter_mut().take(3) {
+                        *c = c.max(0.0);
+                    }
+                    color.v[3] = color.w().clamp(0.0, 1.0);
+                    *dst = space.from_vec4::<InternalType, ExternalType>(color).to_value();
+                }
+            });
+        canvas
+    }
+}
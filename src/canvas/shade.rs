@@ -2,9 +2,10 @@
 use crate::linalg::{Linear, Vec2, Matrix2, Det};
 use crate::types::{
     colortype::{InternalColorType, ColorType},
-    blend::BlendMode
+    blend::BlendMode,
+    gamma::WorkingSpace
 };
-use crate::canvas::BezierCanvas;
+use crate::canvas::{BezierCanvas, Raster, coverage, pixel_to_xy, xy_to_pixel};
 use crate::shading::{VertexShader, FragmentShader, VertexOut};
 
 use rayon::prelude::*;
@@ -16,40 +17,66 @@ impl <InternalType: InternalColorType, ExternalType: ColorType<InternalType>> Be
         VertShader: VertexShader<Attribute = Attribute, Out = Intermediate, Uniform = Uniform>,
         FragShader: FragmentShader<In = Intermediate, Uniform = Uniform, InternalType = InternalType, ExternalType = ExternalType>>
         (&mut self, attribute: &[Attribute], uniform: &Uniform, blend_mode: BlendMode) {
+        self.raster().shade::<Attribute, Uniform, Intermediate, VertShader, FragShader>(attribute, uniform, blend_mode);
+    }
+}
+
+impl <InternalType: InternalColorType, ExternalType: ColorType<InternalType>> Raster<'_, InternalType, ExternalType> {
+    pub(super) fn shade<
+        Attribute: Sync,
+        Uniform: Sync,
+        Intermediate: Linear<f32> + Send + Sync,
+        VertShader: VertexShader<Attribute = Attribute, Out = Intermediate, Uniform = Uniform>,
+        FragShader: FragmentShader<In = Intermediate, Uniform = Uniform, InternalType = InternalType, ExternalType = ExternalType>>
+        (&mut self, attribute: &[Attribute], uniform: &Uniform, blend_mode: BlendMode) {
 
         let mut depth_buffer = vec![f32::NEG_INFINITY; self.width * self.height];
+        let space = self.working_space;
+        let dither = self.dither;
+        let mask = self.mask;
+        let (width, stride) = (self.width, self.stride);
+        let frame = self.frame;
         let out: Vec<VertexOut<Intermediate>> = attribute.into_par_iter()
-            .map(|v| VertShader::shade(v, uniform))
+            .map(|v| {
+                let mut out = VertShader::shade(v, uniform);
+                if space == WorkingSpace::Linear {
+                    VertShader::map_colors(&mut out.varying, |c| space.decode(c));
+                }
+                out
+            })
             .collect();
         for i in (0..out.len()).step_by(3) {
-            let v0 = out[i + 0].coord;
+            let v0 = out[i].coord;
             let v1 = out[i + 1].coord;
             let v2 = out[i + 2].coord;
-            let attr0 = out[i + 0].varying;
+            let attr0 = out[i].varying;
             let attr1 = out[i + 1].varying;
             let attr2 = out[i + 2].varying;
-            let min_x = Self::xy_to_pixel(v0.x()
+            let min_x = xy_to_pixel(v0.x()
                 .clamp(0f32, v1.x())
-                .clamp(0f32, v2.x()), self.width);
-            let max_x = Self::xy_to_pixel(v0.x()
+                .clamp(0f32, v2.x()), frame.width);
+            let max_x = xy_to_pixel(v0.x()
                 .clamp(v1.x(), 1f32)
-                .clamp(v2.x(), 1f32), self.width);
-            let min_y = Self::xy_to_pixel(v0.y()
+                .clamp(v2.x(), 1f32), frame.width);
+            let min_y = xy_to_pixel(v0.y()
                 .clamp(0f32, v1.y())
-                .clamp(0f32, v2.y()), self.height);
-            let max_y = Self::xy_to_pixel(v0.y()
+                .clamp(0f32, v2.y()), frame.height);
+            let max_y = xy_to_pixel(v0.y()
                 .clamp(v1.y(), 1f32)
-                .clamp(v2.y(), 1f32), self.height);
+                .clamp(v2.y(), 1f32), frame.height);
+            let Some((min_x, max_x, min_y, max_y)) = self.clip_bounds(min_x, max_x, min_y, max_y) else {
+                continue;
+            };
 
             let mat = Matrix2 {v: [(v1 - v0).v, (v2 - v0).v]}.transpose();
             let det_mat = mat.det();
             if det_mat.abs() < f32::EPSILON {
                 return;
             }
-            let depth_chunks = depth_buffer.par_chunks_mut(self.width)
+            let depth_chunks = depth_buffer.par_chunks_mut(width)
                 .skip(min_y)
                 .take(max_y + 1 - min_y);
-            let pixel_chunks = self.pixels.par_chunks_mut(self.width)
+            let pixel_chunks = self.rows_mut()
                 .skip(min_y)
                 .take(max_y + 1 - min_y);
 
@@ -68,7 +95,7 @@ impl <InternalType: InternalColorType, ExternalType: ColorType<InternalType>> Be
                     .enumerate()
                     .for_each(|(j, (depth, pixel))| {
                         let x = j + min_x;
-                        let coord = Vec2::new(Self::pixel_to_xy(x, self.width), Self::pixel_to_xy(y, self.height));
+                        let coord = Vec2::new(pixel_to_xy(frame.x + x, frame.width), pixel_to_xy(frame.y + y, frame.height));
                         let ans = coord - v0;
                         let det_t = Matrix2 {
                             v: [[ans.x(), mat.v[0][1]],
@@ -84,14 +111,21 @@ impl <InternalType: InternalColorType, ExternalType: ColorType<InternalType>> Be
                         if t < 0f32 || u < 0f32 || (1f32 - t - u) < 0f32 {
                             return;
                         }
-                        let attrib = 
+                        let mut attrib =
                             attr0 * (1f32 - t - u) +
                             attr1 * t +
                             attr2 * u;
+                        if space == WorkingSpace::Linear {
+                            VertShader::map_colors(&mut attrib, |c| space.encode(c));
+                        }
                         let shaded = FragShader::shade(&attrib, uniform);
                         if shaded.depth > *depth {
                             *depth = shaded.depth;
-                            BezierCanvas::par_set_pixel(pixel, &shaded.color, blend_mode);
+                            let color = match shaded.unquantized() {
+                                Some(raw) => dither.quantize(raw, frame.x + x, frame.y + y),
+                                None => shaded.color,
+                            };
+                            BezierCanvas::<InternalType, ExternalType>::par_set_pixel(pixel, &color, blend_mode, space, coverage(mask, y * stride + x));
                         }
                     })
             });
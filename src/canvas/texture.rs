@@ -1,4 +1,4 @@
-use crate::types::colortype::{InternalColorType, ColorType};
+use crate::types::colortype::{InternalColorType, ColorType, unpremultiply};
 use crate::texture::{SampleFilter, NearestFilter, LinearFilter, CubicFilter, Wrapping, WrapRepeat, WrapClampToEdge};
 use crate::linalg::{Vec2, Vec4};
 use crate::canvas::BezierCanvas;
@@ -12,6 +12,9 @@ use crate::canvas::BezierCanvas;
         the canvas is considered as a continuous field with square pixels, whose edge is on integral xy points. if a point we need to fill is within a pixel, we fill this pixel.
 
     Hereby filtering is treated as a texture.
+
+    Interpolation is done on premultiplied colors, so transparent texels do not bleed their color into the result,
+    and in the working space of the texture; the sampled color is always sRGB encoded.
 */
 // fn point_to_uv(pnt: usize, max: usize) -> f32 {
 //     (pnt as f32) / ((max - 1) as f32)
@@ -47,11 +50,12 @@ impl <InternalType: InternalColorType, ExternalType: ColorType<InternalType>> Sa
         let u1 = u;
         let u0 = 1.0 - u1;
 
-        let p00 = texture.get_pixel(x0, y0).to_vec4();
-        let p01 = texture.get_pixel(x0, y1).to_vec4();
-        let p10 = texture.get_pixel(x1, y0).to_vec4();
-        let p11 = texture.get_pixel(x1, y1).to_vec4();
-        t0 * u0 * p00 + t1 * u0 * p10 +  t0 * u1 * p01 + t1 * u1 * p11
+        let space = texture.working_space;
+        let p00 = space.to_premul_vec4(&texture.get_pixel(x0, y0));
+        let p01 = space.to_premul_vec4(&texture.get_pixel(x0, y1));
+        let p10 = space.to_premul_vec4(&texture.get_pixel(x1, y0));
+        let p11 = space.to_premul_vec4(&texture.get_pixel(x1, y1));
+        space.encode(unpremultiply(t0 * u0 * p00 + t1 * u0 * p10 +  t0 * u1 * p01 + t1 * u1 * p11))
     }
 }
 impl <InternalType: InternalColorType, ExternalType: ColorType<InternalType>> SampleFilter<InternalType, ExternalType> for CubicFilter {
@@ -72,11 +76,12 @@ impl <InternalType: InternalColorType, ExternalType: ColorType<InternalType>> Sa
         let u1 = -2.0 * u * u * u + 3.0 * u * u;
         let u0 = 1.0 - u1;
 
-        let p00 = texture.get_pixel(x0, y0).to_vec4();
-        let p01 = texture.get_pixel(x0, y1).to_vec4();
-        let p10 = texture.get_pixel(x1, y0).to_vec4();
-        let p11 = texture.get_pixel(x1, y1).to_vec4();
-        t0 * u0 * p00 + t1 * u0 * p10 +  t0 * u1 * p01 + t1 * u1 * p11
+        let space = texture.working_space;
+        let p00 = space.to_premul_vec4(&texture.get_pixel(x0, y0));
+        let p01 = space.to_premul_vec4(&texture.get_pixel(x0, y1));
+        let p10 = space.to_premul_vec4(&texture.get_pixel(x1, y0));
+        let p11 = space.to_premul_vec4(&texture.get_pixel(x1, y1));
+        space.encode(unpremultiply(t0 * u0 * p00 + t1 * u0 * p10 +  t0 * u1 * p01 + t1 * u1 * p11))
     }
 }
 
@@ -85,6 +90,10 @@ impl Wrapping for WrapRepeat {
     fn wrap(x: f32) -> f32 {
         x - x.floor()
     }
+    // whole texels repeat with a period of max, not max - 1
+    fn wrap_index(i: isize, max: usize) -> usize {
+        i.rem_euclid(max as isize) as usize
+    }
 }
 impl Wrapping for WrapClampToEdge {
     fn wrap(x: f32) -> f32 {
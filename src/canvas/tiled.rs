@@ -0,0 +1,371 @@
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::marker::PhantomData;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use num::Zero;
+
+use crate::linalg::{Linear, Vec2, Vec4, Matrix23};
+use crate::types::{
+    colortype::{InternalColorType, ColorType},
+    blend::BlendMode,
+    gamma::WorkingSpace
+};
+use crate::texture::SampleFilter;
+use crate::shading::{VertexShader, FragmentShader};
+use crate::convert::PNGSamples;
+use crate::canvas::{BezierCanvas, Frame};
+use crate::canvas::convert::export_png_rows;
+
+enum Tile<InternalType: InternalColorType, ExternalType: ColorType<InternalType>> {
+    // never drawn to, all zero
+    Empty,
+    Resident { canvas: BezierCanvas<InternalType, ExternalType>, last_used: u64 },
+    // written to its slot of the spill file
+    Spilled
+}
+
+// temporary file holding tiles evicted when more than `max_resident` tiles are in memory, one fixed-size slot per tile
+struct Spill {
+    file: File,
+    path: PathBuf,
+    max_resident: usize
+}
+
+// little-endian serialization of pixels, to spill tiles to disk;
+// public in a private module, so it bounds `TiledCanvas` without being nameable or implementable outside the crate
+pub trait SpillBytes: InternalColorType {
+    const BYTES: usize;
+    fn write_bytes(&self, out: &mut Vec<u8>);
+    fn read_bytes(bytes: &[u8]) -> Self;
+}
+impl SpillBytes for u8 {
+    const BYTES: usize = 1;
+    fn write_bytes(&self, out: &mut Vec<u8>) {
+        out.push(*self);
+    }
+    fn read_bytes(bytes: &[u8]) -> Self {
+        bytes[0]
+    }
+}
+impl SpillBytes for u16 {
+    const BYTES: usize = 2;
+    fn write_bytes(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.to_le_bytes());
+    }
+    fn read_bytes(bytes: &[u8]) -> Self {
+        u16::from_le_bytes(bytes[..2].try_into().unwrap())
+    }
+}
+impl SpillBytes for u32 {
+    const BYTES: usize = 4;
+    fn write_bytes(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.to_le_bytes());
+    }
+    fn read_bytes(bytes: &[u8]) -> Self {
+        u32::from_le_bytes(bytes[..4].try_into().unwrap())
+    }
+}
+impl SpillBytes for u64 {
+    const BYTES: usize = 8;
+    fn write_bytes(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.to_le_bytes());
+    }
+    fn read_bytes(bytes: &[u8]) -> Self {
+        u64::from_le_bytes(bytes[..8].try_into().unwrap())
+    }
+}
+impl SpillBytes for Vec4 {
+    const BYTES: usize = 16;
+    fn write_bytes(&self, out: &mut Vec<u8>) {
+        for c in self.v {
+            out.extend_from_slice(&c.to_le_bytes());
+        }
+    }
+    fn read_bytes(bytes: &[u8]) -> Self {
+        let c = |i: usize| f32::from_le_bytes(bytes[4 * i..4 * i + 4].try_into().unwrap());
+        Vec4::new(c(0), c(1), c(2), c(3))
+    }
+}
+
+/*
+    A canvas stored as square tiles, for images too large to hold as a single buffer.
+
+    Tiles are allocated when drawing first touches them, and drawing is routed to the tiles covering its bounding box,
+    with the same coordinates and results as a `BezierCanvas` of the same size:
+    every tile is a frame of the whole canvas, rasterizing in its pixels.
+    With `spill_to_disk`, least recently used tiles are moved to a temporary file to bound memory.
+ */
+pub struct TiledCanvas<InternalType: InternalColorType, ExternalType: ColorType<InternalType>> {
+    pub width: usize,
+    pub height: usize,
+    pub working_space: WorkingSpace,
+    tile_size: usize,
+    columns: usize,
+    tiles: Vec<Tile<InternalType, ExternalType>>,
+    spill: Option<Spill>,
+    // drawing counter, for eviction
+    clock: u64,
+    external_type: PhantomData<ExternalType>
+}
+
+impl <InternalType: SpillBytes, ExternalType: ColorType<InternalType>> TiledCanvas<InternalType, ExternalType> {
+    pub fn new(width: usize, height: usize, tile_size: usize) -> Self {
+        assert!(tile_size > 0);
+        let columns = width.div_ceil(tile_size);
+        let rows = height.div_ceil(tile_size);
+        TiledCanvas {
+            width,
+            height,
+            working_space: WorkingSpace::Gamma,
+            tile_size,
+            columns,
+            tiles: (0..columns * rows).map(|_| Tile::Empty).collect(),
+            spill: None,
+            clock: 0,
+            external_type: PhantomData
+        }
+    }
+
+    // keep at most `max_resident_tiles` tiles in memory, spilling the others to a temporary file removed on drop
+    pub fn spill_to_disk(&mut self, max_resident_tiles: usize) {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        if self.spill.is_none() {
+            let path = std::env::temp_dir().join(format!("bezier-tiles-{}-{}", std::process::id(), COUNTER.fetch_add(1, Ordering::Relaxed)));
+            let file = OpenOptions::new().read(true).write(true).create_new(true).open(&path).unwrap();
+            self.spill = Some(Spill { file, path, max_resident: 0 });
+        }
+        if let Some(spill) = self.spill.as_mut() {
+            spill.max_resident = max_resident_tiles.max(1);
+        }
+        self.evict(None);
+    }
+
+    // number of tiles held in memory
+    pub fn resident_tiles(&self) -> usize {
+        self.tiles.iter().filter(|tile| matches!(tile, Tile::Resident { .. })).count()
+    }
+
+    pub fn get_pixel(&self, x: usize, y: usize) -> ExternalType {
+        assert!(x < self.width && y < self.height);
+        let index = (y / self.tile_size) * self.columns + x / self.tile_size;
+        let (x_0, y_0, width, _) = self.tile_rect(index);
+        let (x, y) = (x - x_0, y - y_0);
+        match &self.tiles[index] {
+            Tile::Empty => ExternalType::from_value(Zero::zero()),
+            Tile::Resident { canvas, .. } => canvas.get_pixel(x, y),
+            Tile::Spilled => {
+                let mut pixel = [InternalType::zero()];
+                self.read_spilled(index, y * width + x, &mut pixel);
+                ExternalType::from_value(pixel[0])
+            },
+        }
+    }
+
+    pub fn fill_rect(&mut self, pos: &Vec2, size: &Vec2, color: &ExternalType, blend_mode: BlendMode) {
+        self.route(pos, &(*pos + *size), |canvas| canvas.fill_rect(pos, size, color, blend_mode));
+    }
+
+    pub fn fill_oval(&mut self, pos: &Vec2, size: &Vec2, color: &ExternalType, blend_mode: BlendMode) {
+        self.route(&(*pos - *size), &(*pos + *size), |canvas| canvas.fill_oval(pos, size, color, blend_mode));
+    }
+
+    pub fn fill_circle(&mut self, pos: &Vec2, radius: f32, color: &ExternalType, blend_mode: BlendMode) {
+        self.fill_oval(pos, &Vec2 {v: [radius, radius]}, color, blend_mode);
+    }
+
+    pub fn stroke_line(&mut self, pos0: &Vec2, pos1: &Vec2, color: &ExternalType, blend_mode: BlendMode) {
+        let (min, max) = bounds([*pos0, *pos1].iter());
+        self.route(&min, &max, |canvas| canvas.stroke_line(pos0, pos1, color, blend_mode));
+    }
+
+    // the curve stays within the bounding box of its control points
+    pub fn stroke_bezier<const N: usize>(&mut self, poses: &[Vec2], color: &ExternalType, stops: usize, blend_mode: BlendMode) {
+        let (min, max) = bounds(poses.iter());
+        self.route(&min, &max, |canvas| canvas.stroke_bezier::<N>(poses, color, stops, blend_mode));
+    }
+
+    pub fn fill_shape(&mut self, contours: &[Vec<Vec2>], color: &ExternalType, blend_mode: BlendMode) {
+        let (min, max) = bounds(contours.iter().flatten());
+        let contours = contours.to_vec();
+        self.route(&min, &max, |canvas| canvas.fill_shape(&contours, color, blend_mode));
+    }
+
+    // vertices are shaded once to find the tiles covered, and again for every tile
+    pub fn shade<
+        Attribute: Sync,
+        Uniform: Sync,
+        Intermediate: Linear<f32> + Send + Sync,
+        VertShader: VertexShader<Attribute = Attribute, Out = Intermediate, Uniform = Uniform>,
+        FragShader: FragmentShader<In = Intermediate, Uniform = Uniform, InternalType = InternalType, ExternalType = ExternalType>>
+        (&mut self, attribute: &[Attribute], uniform: &Uniform, blend_mode: BlendMode) {
+        let coords: Vec<Vec2> = attribute.iter().map(|v| VertShader::shade(v, uniform).coord).collect();
+        let (min, max) = bounds(coords.iter());
+        self.route(&min, &max, |canvas| canvas.shade::<Attribute, Uniform, Intermediate, VertShader, FragShader>(attribute, uniform, blend_mode));
+    }
+
+    pub fn draw_image<
+        SrcInternalType: InternalColorType,
+        SrcExternalType: ColorType<SrcInternalType>,
+        TextureFilter: SampleFilter<SrcInternalType, SrcExternalType>>
+        (&mut self, src: &BezierCanvas<SrcInternalType, SrcExternalType>, transform: &Matrix23, blend_mode: BlendMode, opacity: f32) {
+        let corners = [Vec2::new(0.0, 0.0), Vec2::new(1.0, 0.0), Vec2::new(0.0, 1.0), Vec2::new(1.0, 1.0)]
+            .map(|c| Vec2::new(
+                transform.v[0][0] * c.x() + transform.v[0][1] * c.y() + transform.v[0][2],
+                transform.v[1][0] * c.x() + transform.v[1][1] * c.y() + transform.v[1][2]
+            ));
+        let (min, max) = bounds(corners.iter());
+        self.route(&min, &max, |canvas| canvas.draw_image::<SrcInternalType, SrcExternalType, TextureFilter>(src, transform, blend_mode, opacity));
+    }
+
+    pub fn draw_image_rect<
+        SrcInternalType: InternalColorType,
+        SrcExternalType: ColorType<SrcInternalType>,
+        TextureFilter: SampleFilter<SrcInternalType, SrcExternalType>>
+        (&mut self, src: &BezierCanvas<SrcInternalType, SrcExternalType>, pos: &Vec2, size: &Vec2, blend_mode: BlendMode, opacity: f32) {
+        self.route(pos, &(*pos + *size), |canvas| {
+            canvas.draw_image_rect::<SrcInternalType, SrcExternalType, TextureFilter>(src, pos, size, blend_mode, opacity);
+        });
+    }
+
+    // write the canvas as a PNG one row at a time, reading spilled tiles row by row too
+    pub fn export_png(&self, img_path: &str) where ExternalType: PNGSamples {
+        let mut buffer = Vec::new();
+        export_png_rows::<ExternalType, _>(img_path, self.width, self.height, |y, row| {
+            let tile_row = y / self.tile_size;
+            for column in 0..self.columns {
+                let index = tile_row * self.columns + column;
+                let (_, y_0, width, _) = self.tile_rect(index);
+                let start = (y - y_0) * width;
+                match &self.tiles[index] {
+                    Tile::Empty => row.extend((0..width).map(|_| ExternalType::from_value(Zero::zero()))),
+                    Tile::Resident { canvas, .. } => {
+                        row.extend(canvas.pixels[start..start + width].iter().map(|p| ExternalType::from_value(*p)));
+                    },
+                    Tile::Spilled => {
+                        buffer.resize(width, InternalType::zero());
+                        self.read_spilled(index, start, &mut buffer);
+                        row.extend(buffer.iter().map(|p| ExternalType::from_value(*p)));
+                    },
+                }
+            }
+        });
+    }
+
+    // pixel rect (x, y, width, height) of a tile, smaller than the tile size on the right and bottom edges
+    fn tile_rect(&self, index: usize) -> (usize, usize, usize, usize) {
+        let x = (index % self.columns) * self.tile_size;
+        let y = (index / self.columns) * self.tile_size;
+        (x, y, self.tile_size.min(self.width - x), self.tile_size.min(self.height - y))
+    }
+
+    /*
+        Draw on every tile intersecting the bounding box from `min` to `max`, in canvas coordinates,
+        with a margin of a pixel, as drawing rounds to the nearest pixels.
+     */
+    fn route<F: FnMut(&mut BezierCanvas<InternalType, ExternalType>)>(&mut self, min: &Vec2, max: &Vec2, mut draw: F) {
+        if self.width == 0 || self.height == 0 {
+            return;
+        }
+        let pixel_range = |lo: f32, hi: f32, max: usize| {
+            let lo = (lo * max as f32 - 1.5).floor().clamp(0.0, (max - 1) as f32) as usize;
+            let hi = (hi * max as f32 + 0.5).ceil().clamp(0.0, (max - 1) as f32) as usize;
+            (lo, hi)
+        };
+        let (x_0, x_1) = pixel_range(min.x(), max.x(), self.width);
+        let (y_0, y_1) = pixel_range(min.y(), max.y(), self.height);
+        for row in y_0 / self.tile_size..=y_1 / self.tile_size {
+            for column in x_0 / self.tile_size..=x_1 / self.tile_size {
+                draw(self.tile_mut(row * self.columns + column));
+            }
+        }
+    }
+
+    // load or allocate a tile, spilling others if needed
+    fn tile_mut(&mut self, index: usize) -> &mut BezierCanvas<InternalType, ExternalType> {
+        self.clock += 1;
+        let clock = self.clock;
+        if let Tile::Resident { last_used, .. } = &mut self.tiles[index] {
+            *last_used = clock;
+        } else {
+            let (x, y, width, height) = self.tile_rect(index);
+            let mut canvas = BezierCanvas::new(width, height);
+            canvas.frame = Some(Frame { x, y, width: self.width, height: self.height });
+            if let Tile::Spilled = self.tiles[index] {
+                self.read_spilled(index, 0, &mut canvas.pixels);
+            }
+            self.tiles[index] = Tile::Resident { canvas, last_used: clock };
+            self.evict(Some(index));
+        }
+        let working_space = self.working_space;
+        match &mut self.tiles[index] {
+            Tile::Resident { canvas, .. } => {
+                canvas.working_space = working_space;
+                canvas
+            },
+            _ => unreachable!(),
+        }
+    }
+
+    // spill least recently used tiles until few enough are resident, except `keep`
+    fn evict(&mut self, keep: Option<usize>) {
+        let Some(max_resident) = self.spill.as_ref().map(|spill| spill.max_resident) else {
+            return;
+        };
+        while self.resident_tiles() > max_resident {
+            let oldest = self.tiles.iter()
+                .enumerate()
+                .filter(|(i, _)| Some(*i) != keep)
+                .filter_map(|(i, tile)| match tile {
+                    Tile::Resident { last_used, .. } => Some((i, *last_used)),
+                    _ => None,
+                })
+                .min_by_key(|(_, last_used)| *last_used);
+            let Some((index, _)) = oldest else {
+                return;
+            };
+            let Tile::Resident { canvas, .. } = std::mem::replace(&mut self.tiles[index], Tile::Spilled) else {
+                unreachable!();
+            };
+            let mut bytes = Vec::with_capacity(canvas.pixels.len() * InternalType::BYTES);
+            for pixel in &canvas.pixels {
+                pixel.write_bytes(&mut bytes);
+            }
+            let offset = self.slot_offset(index, 0);
+            let mut file = &self.spill.as_ref().unwrap().file;
+            file.seek(SeekFrom::Start(offset)).unwrap();
+            file.write_all(&bytes).unwrap();
+        }
+    }
+
+    // byte offset of pixel `start` of a tile in the spill file
+    fn slot_offset(&self, index: usize, start: usize) -> u64 {
+        ((index * self.tile_size * self.tile_size + start) * InternalType::BYTES) as u64
+    }
+
+    fn read_spilled(&self, index: usize, start: usize, pixels: &mut [InternalType]) {
+        let mut bytes = vec![0u8; pixels.len() * InternalType::BYTES];
+        let mut file = &self.spill.as_ref().expect("spilled tile without a spill file").file;
+        file.seek(SeekFrom::Start(self.slot_offset(index, start))).unwrap();
+        file.read_exact(&mut bytes).unwrap();
+        for (pixel, chunk) in pixels.iter_mut().zip(bytes.chunks_exact(InternalType::BYTES)) {
+            *pixel = InternalType::read_bytes(chunk);
+        }
+    }
+}
+
+impl <InternalType: InternalColorType, ExternalType: ColorType<InternalType>> Drop for TiledCanvas<InternalType, ExternalType> {
+    fn drop(&mut self) {
+        if let Some(spill) = &self.spill {
+            let _ = std::fs::remove_file(&spill.path);
+        }
+    }
+}
+
+fn bounds<'a, I: Iterator<Item = &'a Vec2>>(points: I) -> (Vec2, Vec2) {
+    points.fold(
+        (Vec2::new(f32::INFINITY, f32::INFINITY), Vec2::new(f32::NEG_INFINITY, f32::NEG_INFINITY)),
+        |(min, max), p| (Vec2::new(min.x().min(p.x()), min.y().min(p.y())), Vec2::new(max.x().max(p.x()), max.y().max(p.y())))
+    )
+}
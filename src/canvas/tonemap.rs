@@ -0,0 +1,28 @@
+use rayon::prelude::*;
+
+use crate::linalg::Vec4;
+use crate::types::{
+    colortype::{InternalColorType, ColorType, RGBAF32},
+    tonemap::ToneMap
+};
+use crate::canvas::BezierCanvas;
+
+impl BezierCanvas<Vec4, RGBAF32> {
+    /*
+        Convert a high dynamic range canvas into a low dynamic range one, e.g. `BezierCanvas<u32, RGBA>` for PNG export.
+
+        The operator is applied in the working space of this canvas, so with `WorkingSpace::Linear` it is applied to linear light,
+        and the result is encoded to sRGB again.
+     */
+    pub fn tone_map<InternalType: InternalColorType, ExternalType: ColorType<InternalType>>(&self, operator: ToneMap, exposure: f32) -> BezierCanvas<InternalType, ExternalType> {
+        let space = self.working_space;
+        let mut canvas = BezierCanvas::new(self.width, self.height);
+        canvas.pixels.par_iter_mut()
+            .zip(self.pixels.par_iter())
+            .for_each(|(dst, src)| {
+                let mapped = operator.map(space.decode(*src), exposure);
+                *dst = ExternalType::from_vec4(space.encode(mapped)).to_value();
+            });
+        canvas
+    }
+}
@@ -0,0 +1,124 @@
+use std::marker::PhantomData;
+
+use crate::linalg::{Linear, Vec2, Matrix23};
+use crate::types::{
+    colortype::{InternalColorType, ColorType},
+    blend::BlendMode
+};
+use crate::texture::SampleFilter;
+use crate::shading::{VertexShader, FragmentShader};
+use crate::canvas::{BezierCanvas, Frame, Raster};
+
+/*
+    A rectangle of a canvas, drawn into with its own normalized coordinates:
+    (0.0, 0.0) is the top-left corner of its top-left pixel, and (1.0, 1.0) the bottom-right corner of its bottom-right pixel.
+
+    The view borrows the rectangle of the canvas buffer, with the row stride of the canvas, and rasterizes in its own pixels,
+    so drawing into a view of the size of a canvas gives the same pixels as drawing into that canvas.
+    Drawing never writes outside the rectangle, and is still scaled by the mask of the canvas, if any.
+ */
+pub struct BezierCanvasViewMut<'a, InternalType: InternalColorType, ExternalType: ColorType<InternalType>> {
+    pub width: usize,
+    pub height: usize,
+    raster: Raster<'a, InternalType, ExternalType>
+}
+
+impl <InternalType: InternalColorType, ExternalType: ColorType<InternalType>> BezierCanvas<InternalType, ExternalType> {
+    // view of the rect at pixel (x, y) of the given size in pixels, clipped to the canvas
+    pub fn view_mut(&mut self, x: usize, y: usize, width: usize, height: usize) -> BezierCanvasViewMut<'_, InternalType, ExternalType> {
+        let x = x.min(self.width);
+        let y = y.min(self.height);
+        let width = width.min(self.width - x);
+        let height = height.min(self.height - y);
+        let stride = self.width;
+        let range = if width == 0 || height == 0 {
+            0..0
+        } else {
+            y * stride + x..(y + height - 1) * stride + x + width
+        };
+        let raster = Raster {
+            pixels: &mut self.pixels[range.clone()],
+            mask: self.mask.as_deref().map(|mask| &mask[range]),
+            width,
+            height,
+            stride,
+            frame: Frame { x: 0, y: 0, width, height },
+            working_space: self.working_space,
+            dither: self.dither,
+            external_type: PhantomData
+        };
+        BezierCanvasViewMut { width, height, raster }
+    }
+}
+
+impl <InternalType: InternalColorType, ExternalType: ColorType<InternalType>> BezierCanvasViewMut<'_, InternalType, ExternalType> {
+    pub fn get_pixel(&self, x: usize, y: usize) -> ExternalType {
+        ExternalType::from_value(self.raw_row(y)[x])
+    }
+
+    // row `y` of the view in the canvas buffer
+    pub fn raw_row(&self, y: usize) -> &[InternalType] {
+        assert!(y < self.height);
+        let start = y * self.raster.stride;
+        &self.raster.pixels[start..start + self.width]
+    }
+
+    pub fn raw_row_mut(&mut self, y: usize) -> &mut [InternalType] {
+        assert!(y < self.height);
+        let start = y * self.raster.stride;
+        &mut self.raster.pixels[start..start + self.width]
+    }
+
+    pub fn fill_rect(&mut self, pos: &Vec2, size: &Vec2, color: &ExternalType, blend_mode: BlendMode) {
+        self.raster.fill_rect(pos, size, color, blend_mode);
+    }
+
+    pub fn fill_oval(&mut self, pos: &Vec2, size: &Vec2, color: &ExternalType, blend_mode: BlendMode) {
+        self.raster.fill_oval(pos, size, color, blend_mode);
+    }
+
+    pub fn fill_circle(&mut self, pos: &Vec2, radius: f32, color: &ExternalType, blend_mode: BlendMode) {
+        self.fill_oval(pos, &Vec2 {v: [radius, radius]}, color, blend_mode);
+    }
+
+    pub fn stroke_line(&mut self, pos0: &Vec2, pos1: &Vec2, color: &ExternalType, blend_mode: BlendMode) {
+        self.raster.stroke_line(pos0, pos1, color, blend_mode);
+    }
+
+    pub fn stroke_bezier<const N: usize>(&mut self, poses: &[Vec2], color: &ExternalType, stops: usize, blend_mode: BlendMode) {
+        self.raster.stroke_bezier::<N>(poses, color, stops, blend_mode);
+    }
+
+    pub fn fill_shape(&mut self, contours: &[Vec<Vec2>], color: &ExternalType, blend_mode: BlendMode) {
+        self.raster.fill_shape(contours, color, blend_mode);
+    }
+
+    // vertex shaders output coordinates of the view
+    pub fn shade<
+        Attribute: Sync,
+        Uniform: Sync,
+        Intermediate: Linear<f32> + Send + Sync,
+        VertShader: VertexShader<Attribute = Attribute, Out = Intermediate, Uniform = Uniform>,
+        FragShader: FragmentShader<In = Intermediate, Uniform = Uniform, InternalType = InternalType, ExternalType = ExternalType>>
+        (&mut self, attribute: &[Attribute], uniform: &Uniform, blend_mode: BlendMode) {
+        self.raster.shade::<Attribute, Uniform, Intermediate, VertShader, FragShader>(attribute, uniform, blend_mode);
+    }
+
+    // `transform` maps canvas coordinates of `src` to coordinates of the view
+    pub fn draw_image<
+        SrcInternalType: InternalColorType,
+        SrcExternalType: ColorType<SrcInternalType>,
+        TextureFilter: SampleFilter<SrcInternalType, SrcExternalType>>
+        (&mut self, src: &BezierCanvas<SrcInternalType, SrcExternalType>, transform: &Matrix23, blend_mode: BlendMode, opacity: f32) {
+        self.raster.draw_image::<SrcInternalType, SrcExternalType, TextureFilter>(src, transform, blend_mode, opacity);
+    }
+
+    pub fn draw_image_rect<
+        SrcInternalType: InternalColorType,
+        SrcExternalType: ColorType<SrcInternalType>,
+        TextureFilter: SampleFilter<SrcInternalType, SrcExternalType>>
+        (&mut self, src: &BezierCanvas<SrcInternalType, SrcExternalType>, pos: &Vec2, size: &Vec2, blend_mode: BlendMode, opacity: f32) {
+        let transform = Matrix23 { v: [[size.x(), 0.0, pos.x()], [0.0, size.y(), pos.y()]] };
+        self.draw_image::<SrcInternalType, SrcExternalType, TextureFilter>(src, &transform, blend_mode, opacity);
+    }
+}
@@ -0,0 +1,306 @@
+// matrices are kept with the precision they are published with
+#![allow(clippy::excessive_precision)]
+
+use crate::linalg::{BMatrix, Matrix3, Vec3, Vec4};
+use crate::types::gamma::{srgb_to_linear, linear_to_srgb};
+
+/*
+    Color spaces convertible from and to the `Vec4` sRGB representation of `ColorType::to_vec4`.
+
+    Alpha is always carried in `w` unchanged. The other components are:
+        SRGB:       r, g, b in [0, 1]
+        LinearSRGB: r, g, b in linear light
+        HSV, HSL:   hue in degrees [0, 360), saturation and value / lightness in [0, 1]
+        Lab:        CIE L* in [0, 100], a*, b*, with D65 white
+        LCh:        CIE L*, chroma, hue in degrees
+        OkLab:      L in [0, 1], a, b
+        OkLCh:      L, chroma, hue in degrees
+
+    Conversions back to sRGB are not clamped, so colors out of the sRGB gamut come back out of [0, 1].
+ */
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ColorSpace {
+    SRGB,
+    LinearSRGB,
+    HSV,
+    HSL,
+    Lab,
+    LCh,
+    OkLab,
+    OkLCh
+}
+
+const SRGB_TO_XYZ: Matrix3 = BMatrix { v: [
+    [0.4124564, 0.3575761, 0.1804375],
+    [0.2126729, 0.7151522, 0.0721750],
+    [0.0193339, 0.1191920, 0.9503041]
+]};
+const XYZ_TO_SRGB: Matrix3 = BMatrix { v: [
+    [ 3.2404542, -1.5371385, -0.4985314],
+    [-0.9692660,  1.8760108,  0.0415560],
+    [ 0.0556434, -0.2040259,  1.0572252]
+]};
+const D65: [f32; 3] = [0.95047, 1.0, 1.08883];
+
+const SRGB_TO_LMS: Matrix3 = BMatrix { v: [
+    [0.4122214708, 0.5363325363, 0.0514459929],
+    [0.2119034982, 0.6806995451, 0.1073969566],
+    [0.0883024619, 0.2817188376, 0.6299787005]
+]};
+const LMS_TO_OKLAB: Matrix3 = BMatrix { v: [
+    [0.2104542553,  0.7936177850, -0.0040720468],
+    [1.9779984951, -2.4285922050,  0.4505937099],
+    [0.0259040371,  0.7827717662, -0.8086757660]
+]};
+const OKLAB_TO_LMS: Matrix3 = BMatrix { v: [
+    [1.0,  0.3963377774,  0.2158037573],
+    [1.0, -0.1055613458, -0.0638541728],
+    [1.0, -0.0894841775, -1.2914855480]
+]};
+const LMS_TO_SRGB: Matrix3 = BMatrix { v: [
+    [ 4.0767416621, -3.3077115913,  0.2309699292],
+    [-1.2684380046,  2.6097574011, -0.3413193965],
+    [-0.0041960863, -0.7034186147,  1.7076147010]
+]};
+
+impl ColorSpace {
+    pub fn from_rgb(&self, rgba: Vec4) -> Vec4 {
+        let rgb = rgba.xyz();
+        let ans = match self {
+            ColorSpace::SRGB => rgb,
+            ColorSpace::LinearSRGB => map3(rgb, srgb_to_linear),
+            ColorSpace::HSV => rgb_to_hsv(rgb),
+            ColorSpace::HSL => rgb_to_hsl(rgb),
+            ColorSpace::Lab => rgb_to_lab(rgb),
+            ColorSpace::LCh => lab_to_lch(rgb_to_lab(rgb)),
+            ColorSpace::OkLab => rgb_to_oklab(rgb),
+            ColorSpace::OkLCh => lab_to_lch(rgb_to_oklab(rgb)),
+        };
+        Vec4::new(ans.x(), ans.y(), ans.z(), rgba.w())
+    }
+
+    pub fn to_rgb(&self, color: Vec4) -> Vec4 {
+        let raw = color.xyz();
+        let ans = match self {
+            ColorSpace::SRGB => raw,
+            ColorSpace::LinearSRGB => map3(raw, linear_to_srgb),
+            ColorSpace::HSV => hsv_to_rgb(raw),
+            ColorSpace::HSL => hsl_to_rgb(raw),
+            ColorSpace::Lab => lab_to_rgb(raw),
+            ColorSpace::LCh => lab_to_rgb(lch_to_lab(raw)),
+            ColorSpace::OkLab => oklab_to_rgb(raw),
+            ColorSpace::OkLCh => oklab_to_rgb(lch_to_lab(raw)),
+        };
+        Vec4::new(ans.x(), ans.y(), ans.z(), color.w())
+    }
+
+    // index of the hue component in cylindrical spaces
+    fn hue_index(&self) -> Option<usize> {
+        match self {
+            ColorSpace::HSV | ColorSpace::HSL => Some(0),
+            ColorSpace::LCh | ColorSpace::OkLCh => Some(2),
+            _ => None,
+        }
+    }
+
+    /*
+        Interpolate two sRGB colors in this space, t = 0 gives `from` and t = 1 gives `to`.
+        Hue is interpolated along the shorter arc.
+     */
+    pub fn mix(&self, from: Vec4, to: Vec4, t: f32) -> Vec4 {
+        let a = self.from_rgb(from);
+        let b = self.from_rgb(to);
+        let mut ans = a * (1.0 - t) + b * t;
+        if let Some(h) = self.hue_index() {
+            let mut delta = b.v[h] - a.v[h];
+            if delta > 180.0 {
+                delta -= 360.0;
+            } else if delta < -180.0 {
+                delta += 360.0;
+            }
+            ans.v[h] = (a.v[h] + delta * t).rem_euclid(360.0);
+        }
+        self.to_rgb(ans)
+    }
+}
+
+fn map3(c: Vec3, f: fn(f32) -> f32) -> Vec3 {
+    Vec3::new(f(c.x()), f(c.y()), f(c.z()))
+}
+
+fn hue(r: f32, g: f32, b: f32, max: f32, delta: f32) -> f32 {
+    if delta <= 0.0 {
+        0.0
+    } else if max == r {
+        (60.0 * (g - b) / delta).rem_euclid(360.0)
+    } else if max == g {
+        60.0 * ((b - r) / delta + 2.0)
+    } else {
+        60.0 * ((r - g) / delta + 4.0)
+    }
+}
+
+// rgb of a hue with chroma c, before adding the lightness offset
+fn hue_chroma_to_rgb(h: f32, c: f32) -> Vec3 {
+    let h = h.rem_euclid(360.0) / 60.0;
+    let x = c * (1.0 - (h % 2.0 - 1.0).abs());
+    match h as usize {
+        0 => Vec3::new(c, x, 0.0),
+        1 => Vec3::new(x, c, 0.0),
+        2 => Vec3::new(0.0, c, x),
+        3 => Vec3::new(0.0, x, c),
+        4 => Vec3::new(x, 0.0, c),
+        _ => Vec3::new(c, 0.0, x),
+    }
+}
+
+pub fn rgb_to_hsv(rgb: Vec3) -> Vec3 {
+    let (r, g, b) = (rgb.x(), rgb.y(), rgb.z());
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let delta = max - min;
+    let s = if max <= 0.0 { 0.0 } else { delta / max };
+    Vec3::new(hue(r, g, b, max, delta), s, max)
+}
+
+pub fn hsv_to_rgb(hsv: Vec3) -> Vec3 {
+    let c = hsv.z() * hsv.y();
+    let m = hsv.z() - c;
+    let rgb = hue_chroma_to_rgb(hsv.x(), c);
+    Vec3::new(rgb.x() + m, rgb.y() + m, rgb.z() + m)
+}
+
+pub fn rgb_to_hsl(rgb: Vec3) -> Vec3 {
+    let (r, g, b) = (rgb.x(), rgb.y(), rgb.z());
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let delta = max - min;
+    let l = (max + min) / 2.0;
+    let s = if delta <= 0.0 { 0.0 } else { delta / (1.0 - (2.0 * l - 1.0).abs()) };
+    Vec3::new(hue(r, g, b, max, delta), s, l)
+}
+
+pub fn hsl_to_rgb(hsl: Vec3) -> Vec3 {
+    let c = (1.0 - (2.0 * hsl.z() - 1.0).abs()) * hsl.y();
+    let m = hsl.z() - c / 2.0;
+    let rgb = hue_chroma_to_rgb(hsl.x(), c);
+    Vec3::new(rgb.x() + m, rgb.y() + m, rgb.z() + m)
+}
+
+fn lab_f(t: f32) -> f32 {
+    const DELTA: f32 = 6.0 / 29.0;
+    if t > DELTA * DELTA * DELTA {
+        t.cbrt()
+    } else {
+        t / (3.0 * DELTA * DELTA) + 4.0 / 29.0
+    }
+}
+fn lab_f_inv(t: f32) -> f32 {
+    const DELTA: f32 = 6.0 / 29.0;
+    if t > DELTA {
+        t * t * t
+    } else {
+        3.0 * DELTA * DELTA * (t - 4.0 / 29.0)
+    }
+}
+
+pub fn rgb_to_lab(rgb: Vec3) -> Vec3 {
+    let xyz = SRGB_TO_XYZ * map3(rgb, srgb_to_linear);
+    let fx = lab_f(xyz.x() / D65[0]);
+    let fy = lab_f(xyz.y() / D65[1]);
+    let fz = lab_f(xyz.z() / D65[2]);
+    Vec3::new(116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz))
+}
+
+pub fn lab_to_rgb(lab: Vec3) -> Vec3 {
+    let fy = (lab.x() + 16.0) / 116.0;
+    let fx = fy + lab.y() / 500.0;
+    let fz = fy - lab.z() / 200.0;
+    let xyz = Vec3::new(lab_f_inv(fx) * D65[0], lab_f_inv(fy) * D65[1], lab_f_inv(fz) * D65[2]);
+    map3(XYZ_TO_SRGB * xyz, linear_to_srgb)
+}
+
+// also converts OkLab to OkLCh
+pub fn lab_to_lch(lab: Vec3) -> Vec3 {
+    let c = (lab.y() * lab.y() + lab.z() * lab.z()).sqrt();
+    let h = lab.z().atan2(lab.y()).to_degrees().rem_euclid(360.0);
+    Vec3::new(lab.x(), c, h)
+}
+
+pub fn lch_to_lab(lch: Vec3) -> Vec3 {
+    let h = lch.z().to_radians();
+    Vec3::new(lch.x(), lch.y() * h.cos(), lch.y() * h.sin())
+}
+
+pub fn rgb_to_oklab(rgb: Vec3) -> Vec3 {
+    let lms = SRGB_TO_LMS * map3(rgb, srgb_to_linear);
+    LMS_TO_OKLAB * map3(lms, f32::cbrt)
+}
+
+pub fn oklab_to_rgb(lab: Vec3) -> Vec3 {
+    let lms = map3(OKLAB_TO_LMS * lab, |x| x * x * x);
+    map3(LMS_TO_SRGB * lms, linear_to_srgb)
+}
+
+// CIE76 color difference of two Lab colors, also usable with OkLab colors
+pub fn delta_e76(lab0: Vec4, lab1: Vec4) -> f32 {
+    (lab0.xyz() - lab1.xyz()).norm()
+}
+
+// CIEDE2000 color difference of two Lab colors
+pub fn delta_e2000(lab0: Vec4, lab1: Vec4) -> f32 {
+    let (l0, a0, b0) = (lab0.x(), lab0.y(), lab0.z());
+    let (l1, a1, b1) = (lab1.x(), lab1.y(), lab1.z());
+    const POW25_7: f32 = 6103515625.0;
+
+    let c_bar = ((a0 * a0 + b0 * b0).sqrt() + (a1 * a1 + b1 * b1).sqrt()) / 2.0;
+    let g = 0.5 * (1.0 - (c_bar.powi(7) / (c_bar.powi(7) + POW25_7)).sqrt());
+    let a0 = a0 * (1.0 + g);
+    let a1 = a1 * (1.0 + g);
+    let c0 = (a0 * a0 + b0 * b0).sqrt();
+    let c1 = (a1 * a1 + b1 * b1).sqrt();
+    let h0 = if c0 == 0.0 { 0.0 } else { b0.atan2(a0).to_degrees().rem_euclid(360.0) };
+    let h1 = if c1 == 0.0 { 0.0 } else { b1.atan2(a1).to_degrees().rem_euclid(360.0) };
+
+    let delta_l = l1 - l0;
+    let delta_c = c1 - c0;
+    let delta_h = if c0 * c1 == 0.0 {
+        0.0
+    } else if (h1 - h0).abs() <= 180.0 {
+        h1 - h0
+    } else if h1 - h0 > 180.0 {
+        h1 - h0 - 360.0
+    } else {
+        h1 - h0 + 360.0
+    };
+    let delta_h = 2.0 * (c0 * c1).sqrt() * (delta_h.to_radians() / 2.0).sin();
+
+    let l_bar = (l0 + l1) / 2.0;
+    let c_bar = (c0 + c1) / 2.0;
+    let h_bar = if c0 * c1 == 0.0 {
+        h0 + h1
+    } else if (h0 - h1).abs() <= 180.0 {
+        (h0 + h1) / 2.0
+    } else if h0 + h1 < 360.0 {
+        (h0 + h1 + 360.0) / 2.0
+    } else {
+        (h0 + h1 - 360.0) / 2.0
+    };
+
+    let t = 1.0
+        - 0.17 * (h_bar - 30.0).to_radians().cos()
+        + 0.24 * (2.0 * h_bar).to_radians().cos()
+        + 0.32 * (3.0 * h_bar + 6.0).to_radians().cos()
+        - 0.20 * (4.0 * h_bar - 63.0).to_radians().cos();
+    let delta_theta = 30.0 * (-((h_bar - 275.0) / 25.0).powi(2)).exp();
+    let r_c = 2.0 * (c_bar.powi(7) / (c_bar.powi(7) + POW25_7)).sqrt();
+    let s_l = 1.0 + 0.015 * (l_bar - 50.0).powi(2) / (20.0 + (l_bar - 50.0).powi(2)).sqrt();
+    let s_c = 1.0 + 0.045 * c_bar;
+    let s_h = 1.0 + 0.015 * c_bar * t;
+    let r_t = -(2.0 * delta_theta).to_radians().sin() * r_c;
+
+    let l = delta_l / s_l;
+    let c = delta_c / s_c;
+    let h = delta_h / s_h;
+    (l * l + c * c + h * h + r_t * c * h).sqrt()
+}
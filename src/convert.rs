@@ -2,4 +2,9 @@
 pub trait PNGCompatible {
     fn export_png(&self, img_path: &str);
     fn from_png(img_path: &str) -> Self;
-}
\ No newline at end of file
+}
+// PNG encoding of single pixels, for encoders writing one row at a time
+pub trait PNGSamples {
+    fn png_format() -> (png::ColorType, png::BitDepth);
+    fn write_samples(&self, out: &mut Vec<u8>);
+}
@@ -5,6 +5,7 @@ pub mod shading;
 pub mod linalg;
 pub mod texture;
 pub mod convert;
+pub mod colorspace;
 
 #[cfg(test)]
 mod tests;
\ No newline at end of file
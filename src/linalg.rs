@@ -115,7 +115,7 @@ impl <T: Float, const N: usize> BVec<T, N> {
         ans.sqrt()
     }
     pub fn normalize(&self) -> BVec<T, N> {
-        return self.clone() * (T::one() / self.norm())
+        *self * (T::one() / self.norm())
     }
     pub fn star(&self, rhs: &Self) -> BVec<T, N> {
         let mut ans = BVec::zero();
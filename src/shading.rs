@@ -1,7 +1,7 @@
 use std::marker::PhantomData;
 
 use crate::types::colortype::{InternalColorType, ColorType};
-use crate::linalg::{Linear, Vec2};
+use crate::linalg::{Linear, Vec2, Vec4};
 
 
 
@@ -20,12 +20,20 @@ impl <T: Linear<f32>> VertexOut<T> {
 pub struct FragOut<InternalType: InternalColorType, ExternalType: ColorType<InternalType>> {
     pub color: ExternalType,
     pub depth: f32,
+    // color before quantization, dithered by the canvas when present
+    unquantized: Option<Vec4>,
     phantom_data: PhantomData<InternalType>
 }
 
 impl <InternalType: InternalColorType, ExternalType: ColorType<InternalType>> FragOut<InternalType, ExternalType> {
     pub fn new(color: ExternalType, depth: f32) -> Self {
-        FragOut { color: color, depth: depth, phantom_data: PhantomData }
+        FragOut { color, depth, unquantized: None, phantom_data: PhantomData }
+    }
+    pub fn from_vec4(color: Vec4, depth: f32) -> Self {
+        FragOut { color: ExternalType::from_vec4(color), depth, unquantized: Some(color), phantom_data: PhantomData }
+    }
+    pub fn unquantized(&self) -> Option<Vec4> {
+        self.unquantized
     }
 }
 
@@ -34,6 +42,12 @@ pub trait VertexShader {
     type Uniform: Sync;
     type Out: Linear<f32> + Send + Sync;
     fn shade(attr: &Self::Attribute, uniform: &Self::Uniform) -> VertexOut<Self::Out>;
+    /*
+        Apply `f` to the straight-alpha sRGB colors among the varyings; varyings without colors need not override it.
+        In a linear working space, the canvas decodes these colors before they are interpolated and encodes them again
+        before fragment shading, so gradients between vertex colors are interpolated in linear light.
+     */
+    fn map_colors<F: Fn(Vec4) -> Vec4>(_varying: &mut Self::Out, _f: F) {}
 }
 pub trait FragmentShader {
     type Uniform: Sync;
@@ -1,11 +1,24 @@
 use num::One;
 
-use crate::canvas::BezierCanvas;
+use crate::canvas::{BezierCanvas, TiledCanvas, assert_canvas_matches};
 use crate::convert::PNGCompatible;
-use crate::linalg::{Vec2, Matrix2, Det, Vec4};
-use crate::texture::{LinearFilter, WrapClampToEdge};
-use crate::types::colortype::{RGB, RGBA};
+use crate::colorspace::{ColorSpace, delta_e76, delta_e2000};
+use crate::linalg::{BMatrix, BVec, Vec2, Matrix2, Det, Vec4};
+use crate::texture::{LinearFilter, NearestFilter, WrapClampToEdge, WrapRepeat, Wrapping};
+use crate::types::colortype::{ColorType, IntegerColorType, A, R, RA, ARGB, BGRA, RGB565, RGBA4444, PremulRGBA, RGB, RGBA, RGB16, RGBA16, R16, RGBAF32};
+use crate::types::tonemap::ToneMap;
+use crate::types::dither::Dither;
+use crate::types::palette::PaletteMethod;
+use crate::types::mask::MaskMode;
 use crate::types::blend::BlendMode;
+use crate::types::resize::ResizeKernel;
+use crate::types::kernel::{Kernel, EdgeOperator};
+use crate::types::colormatrix::{ColorMatrix, Transfer};
+use crate::types::morphology::StructuringElement;
+use crate::types::conversion::{Conversion, Luma};
+use crate::types::curve::Curve;
+use crate::types::fill::Connectivity;
+use crate::types::gamma::{WorkingSpace, linear_to_srgb, srgb_to_linear};
 
 mod shader;
 #[test]
@@ -89,4 +102,972 @@ fn fill_shape() {
     canvas.fill_shape(&vec![contour1, contour2], &RGB{r: 255, g: 255, b: 255}, BlendMode::Override);
 
     canvas.export_png("target/debug/examples/shape.png");
-}
\ No newline at end of file
+}
+#[test]
+fn separable_blend_modes() {
+    let bg = RGB { r: 64, g: 128, b: 255 };
+    let fg = RGB { r: 192, g: 128, b: 0 };
+    let blend = |mode: BlendMode| RGB::from_value(mode.blend(bg.to_value(), &fg));
+
+    assert_eq!(blend(BlendMode::Darken), RGB { r: 64, g: 128, b: 0 });
+    assert_eq!(blend(BlendMode::Lighten), RGB { r: 192, g: 128, b: 255 });
+    assert_eq!(blend(BlendMode::Difference), RGB { r: 128, g: 0, b: 255 });
+    assert_eq!(blend(BlendMode::Exclusion), RGB { r: 160, g: 127, b: 255 });
+    assert_eq!(blend(BlendMode::Overlay), RGB { r: 96, g: 128, b: 255 });
+    assert_eq!(blend(BlendMode::HardLight), RGB { r: 161, g: 128, b: 0 });
+    assert_eq!(blend(BlendMode::ColorDodge), RGB { r: 255, g: 255, b: 255 });
+    assert_eq!(blend(BlendMode::ColorBurn), RGB { r: 1, g: 2, b: 255 });
+
+    // a half-transparent source over an opaque backdrop is mixed by half
+    let mut canvas = BezierCanvas::<u32, RGBA>::new(4, 4);
+    canvas.fill_rect(&Vec2::new(0.0, 0.0), &Vec2::new(1.0, 1.0), &RGBA { r: 255, g: 255, b: 255, a: 255 }, BlendMode::Override);
+    canvas.fill_rect(&Vec2::new(0.0, 0.0), &Vec2::new(1.0, 1.0), &RGBA { r: 0, g: 0, b: 0, a: 128 }, BlendMode::Multiply);
+    assert_eq!(canvas.get_pixel(1, 1), RGBA { r: 127, g: 127, b: 127, a: 255 });
+
+    // nothing under the source: blend mode falls back to the source color
+    let mut canvas = BezierCanvas::<u32, RGBA>::new(4, 4);
+    canvas.fill_rect(&Vec2::new(0.0, 0.0), &Vec2::new(1.0, 1.0), &RGBA { r: 10, g: 20, b: 30, a: 255 }, BlendMode::Difference);
+    assert_eq!(canvas.get_pixel(1, 1), RGBA { r: 10, g: 20, b: 30, a: 255 });
+}
+
+#[test]
+fn non_separable_blend_modes() {
+    let bg = RGB { r: 200, g: 100, b: 50 };
+    let fg = RGB { r: 40, g: 80, b: 160 };
+    let blend = |mode: BlendMode| RGB::from_value(mode.blend(bg.to_value(), &fg)).to_vec4();
+    let lum = |c: Vec4| 0.3 * c.x() + 0.59 * c.y() + 0.11 * c.z();
+    let sat = |c: Vec4| c.x().max(c.y()).max(c.z()) - c.x().min(c.y()).min(c.z());
+    let (bg, fg) = (bg.to_vec4(), fg.to_vec4());
+
+    // luminosity keeps the backdrop hue and saturation, but takes the source luminance
+    assert!((lum(blend(BlendMode::Luminosity)) - lum(fg)).abs() < 0.01);
+    // color and hue keep the backdrop luminance
+    assert!((lum(blend(BlendMode::Color)) - lum(bg)).abs() < 0.01);
+    assert!((lum(blend(BlendMode::Hue)) - lum(bg)).abs() < 0.01);
+    // saturation keeps the backdrop luminance and takes the source saturation
+    let saturated = blend(BlendMode::Saturation);
+    assert!((lum(saturated) - lum(bg)).abs() < 0.01);
+    assert!((sat(saturated) - sat(fg)).abs() < 0.01);
+    // hue of a gray source is gray
+    let gray = RGB { r: 128, g: 128, b: 128 };
+    let hued = RGB::from_value(BlendMode::Hue.blend(RGB { r: 200, g: 100, b: 50 }.to_value(), &gray));
+    assert_eq!(hued.r, hued.g);
+    assert_eq!(hued.g, hued.b);
+}
+
+#[test]
+fn premultiplied_alpha() {
+    let color = PremulRGBA::from_vec4(Vec4::new(1.0, 0.5, 0.0, 0.5));
+    assert_eq!(color, PremulRGBA { r: 128, g: 64, b: 0, a: 128 });
+    assert_eq!(PremulRGBA { r: 0, g: 0, b: 0, a: 0 }.to_vec4(), Vec4::new(0.0, 0.0, 0.0, 0.0));
+
+    // a transparent texel does not darken an opaque neighbor when filtered
+    let mut texture = BezierCanvas::<u32, PremulRGBA>::new(2, 1);
+    texture.fill_rect(&Vec2::new(0.0, 0.0), &Vec2::new(0.25, 1.0), &PremulRGBA { r: 255, g: 0, b: 0, a: 255 }, BlendMode::Override);
+    let sampled = texture.sample::<LinearFilter, WrapClampToEdge, WrapClampToEdge>(&Vec2::new(0.5, 0.0));
+    assert_eq!(sampled, Vec4::new(1.0, 0.0, 0.0, 0.5));
+
+    let mut canvas = BezierCanvas::<u32, PremulRGBA>::new(4, 4);
+    canvas.fill_rect(&Vec2::new(0.0, 0.0), &Vec2::new(1.0, 1.0), &PremulRGBA::from_vec4(Vec4::new(0.0, 0.0, 1.0, 0.5)), BlendMode::Alpha);
+    canvas.fill_rect(&Vec2::new(0.0, 0.0), &Vec2::new(1.0, 1.0), &PremulRGBA::from_vec4(Vec4::new(1.0, 0.0, 0.0, 0.5)), BlendMode::Alpha);
+    assert_eq!(canvas.get_pixel(1, 1), PremulRGBA { r: 128, g: 0, b: 64, a: 192 });
+
+    canvas.export_png("target/debug/examples/premul.png");
+    let loaded = BezierCanvas::<u32, PremulRGBA>::from_png("target/debug/examples/premul.png");
+    assert_eq!(loaded.get_pixel(1, 1), PremulRGBA { r: 128, g: 0, b: 64, a: 192 });
+}
+
+#[test]
+fn linear_light() {
+    let mut canvas = BezierCanvas::<u32, RGB>::new(2, 1);
+    canvas.working_space = WorkingSpace::Linear;
+    canvas.fill_rect(&Vec2::new(0.0, 0.0), &Vec2::new(1.0, 1.0), &RGB { r: 0, g: 0, b: 0 }, BlendMode::Override);
+    canvas.fill_rect(&Vec2::new(0.5, 0.0), &Vec2::new(0.5, 1.0), &RGB { r: 255, g: 255, b: 255 }, BlendMode::Override);
+    // half the light of white is much brighter than 128 once sRGB encoded
+    let sampled = canvas.sample::<LinearFilter, WrapClampToEdge, WrapClampToEdge>(&Vec2::new(0.5, 0.0));
+    assert_eq!(RGB::from_vec4(sampled), RGB { r: 188, g: 188, b: 188 });
+    canvas.working_space = WorkingSpace::Gamma;
+    let sampled = canvas.sample::<LinearFilter, WrapClampToEdge, WrapClampToEdge>(&Vec2::new(0.5, 0.0));
+    assert_eq!(RGB::from_vec4(sampled), RGB { r: 128, g: 128, b: 128 });
+
+    let mut canvas = BezierCanvas::<u32, RGBA>::new(1, 1);
+    canvas.working_space = WorkingSpace::Linear;
+    canvas.fill_rect(&Vec2::new(0.0, 0.0), &Vec2::new(1.0, 1.0), &RGBA { r: 255, g: 0, b: 0, a: 255 }, BlendMode::Override);
+    canvas.fill_rect(&Vec2::new(0.0, 0.0), &Vec2::new(1.0, 1.0), &RGBA { r: 0, g: 255, b: 0, a: 128 }, BlendMode::Alpha);
+    assert_eq!(canvas.get_pixel(0, 0), RGBA { r: 187, g: 188, b: 0, a: 255 });
+
+    for i in 0..=255u8 {
+        let c = i as f32 / 255.0;
+        assert!((linear_to_srgb(srgb_to_linear(c)) - c).abs() < 1e-4);
+    }
+
+    // exports are tagged as sRGB
+    canvas.export_png("target/debug/examples/linear_light.png");
+    let reader = png::Decoder::new(std::fs::File::open("target/debug/examples/linear_light.png").unwrap()).read_info().unwrap();
+    assert_eq!(reader.info().srgb, Some(png::SrgbRenderingIntent::Perceptual));
+}
+
+#[test]
+fn sixteen_bit_png() {
+    let mut canvas = BezierCanvas::<u64, RGBA16>::new(8, 8);
+    let color = RGBA16 { r: 0x1234, g: 0xfedc, b: 0x0001, a: 0x8000 };
+    canvas.fill_rect(&Vec2::new(0.0, 0.0), &Vec2::new(0.5, 1.0), &color, BlendMode::Override);
+    canvas.export_png("target/debug/examples/rgba16.png");
+    let loaded = BezierCanvas::<u64, RGBA16>::from_png("target/debug/examples/rgba16.png");
+    assert_eq!(loaded.get_pixel(1, 1), color);
+    assert_eq!(loaded.get_pixel(7, 7), RGBA16 { r: 0, g: 0, b: 0, a: 0 });
+
+    let mut canvas = BezierCanvas::<u16, R16>::new(8, 8);
+    canvas.fill_rect(&Vec2::new(0.0, 0.0), &Vec2::new(1.0, 1.0), &R16 { r: 0x0102 }, BlendMode::Override);
+    canvas.export_png("target/debug/examples/gray16.png");
+    let loaded = BezierCanvas::<u16, R16>::from_png("target/debug/examples/gray16.png");
+    assert_eq!(loaded.get_pixel(3, 3), R16 { r: 0x0102 });
+
+    // 8-bit images are widened to the full 16-bit range
+    let mut canvas = BezierCanvas::<u32, RGB>::new(8, 8);
+    canvas.fill_rect(&Vec2::new(0.0, 0.0), &Vec2::new(1.0, 1.0), &RGB { r: 255, g: 128, b: 0 }, BlendMode::Override);
+    canvas.export_png("target/debug/examples/rgb8.png");
+    let loaded = BezierCanvas::<u64, RGB16>::from_png("target/debug/examples/rgb8.png");
+    assert_eq!(loaded.get_pixel(3, 3), RGB16 { r: 0xffff, g: 0x8080, b: 0 });
+
+    // so are images of fewer bits, and palettes are resolved
+    let write_png = |path: &str, color_type: png::ColorType, depth: png::BitDepth, palette: Option<Vec<u8>>, data: &[u8]| {
+        let mut encoder = png::Encoder::new(std::fs::File::create(path).unwrap(), 4, 1);
+        encoder.set_color(color_type);
+        encoder.set_depth(depth);
+        if let Some(palette) = palette {
+            encoder.set_palette(palette);
+        }
+        encoder.write_header().unwrap().write_image_data(data).unwrap();
+    };
+    write_png("target/debug/examples/gray2.png", png::ColorType::Grayscale, png::BitDepth::Two, None, &[0b00_01_10_11]);
+    let loaded = BezierCanvas::<u16, R16>::from_png("target/debug/examples/gray2.png");
+    assert_eq!((0..4).map(|x| loaded.get_pixel(x, 0).r).collect::<Vec<_>>(), vec![0, 0x5555, 0xaaaa, 0xffff]);
+    let palette = vec![255, 0, 0, 0, 255, 0, 0, 0, 255, 255, 255, 255];
+    write_png("target/debug/examples/indexed4.png", png::ColorType::Indexed, png::BitDepth::Four, Some(palette), &[0x01, 0x23]);
+    let loaded = BezierCanvas::<u64, RGBA16>::from_png("target/debug/examples/indexed4.png");
+    assert_eq!(loaded.get_pixel(1, 0), RGBA16 { r: 0, g: 0xffff, b: 0, a: 0xffff });
+    assert_eq!(loaded.get_pixel(3, 0), RGBA16 { r: 0xffff, g: 0xffff, b: 0xffff, a: 0xffff });
+}
+
+#[test]
+fn hdr_tone_mapping() {
+    let mut canvas = BezierCanvas::<Vec4, RGBAF32>::new(4, 4);
+    canvas.fill_rect(&Vec2::new(0.0, 0.0), &Vec2::new(1.0, 1.0), &RGBAF32 { r: 4.0, g: 1.0, b: 0.0, a: 1.0 }, BlendMode::Override);
+    canvas.fill_rect(&Vec2::new(0.0, 0.0), &Vec2::new(1.0, 1.0), &RGBAF32 { r: 4.0, g: 0.5, b: 0.25, a: 1.0 }, BlendMode::Multiply);
+    // values above 1.0 are not clipped
+    assert_eq!(canvas.get_pixel(1, 1), RGBAF32 { r: 16.0, g: 0.5, b: 0.0, a: 1.0 });
+
+    canvas.fill_rect(&Vec2::new(0.0, 0.0), &Vec2::new(1.0, 1.0), &RGBAF32 { r: 4.0, g: 1.0, b: 0.0, a: 1.0 }, BlendMode::Override);
+    let ldr = canvas.tone_map::<u32, RGBA>(ToneMap::Clamp, -1.0);
+    assert_eq!(ldr.get_pixel(1, 1), RGBA { r: 255, g: 128, b: 0, a: 255 });
+    let ldr = canvas.tone_map::<u32, RGBA>(ToneMap::Reinhard, 0.0);
+    assert_eq!(ldr.get_pixel(1, 1), RGBA { r: 204, g: 128, b: 0, a: 255 });
+    let ldr = canvas.tone_map::<u32, RGBA>(ToneMap::ExtendedReinhard(4.0), 0.0);
+    assert_eq!(ldr.get_pixel(1, 1), RGBA { r: 255, g: 135, b: 0, a: 255 });
+    let ldr = canvas.tone_map::<u32, RGBA>(ToneMap::Aces, 0.0);
+    assert_eq!(ldr.get_pixel(1, 1), RGBA { r: 248, g: 205, b: 0, a: 255 });
+    ldr.export_png("target/debug/examples/tonemap.png");
+
+    // integer color types keep `Eq` through their own bound
+    fn distinct<C: IntegerColorType<u32>>(colors: &[C]) -> usize {
+        colors.iter().enumerate().filter(|(i, c)| !colors[..*i].contains(c)).count()
+    }
+    assert_eq!(distinct(&[ldr.get_pixel(0, 0), ldr.get_pixel(1, 1), RGBA { r: 0, g: 0, b: 0, a: 0 }]), 2);
+}
+
+#[test]
+fn packed_formats() {
+    let mut canvas = BezierCanvas::<u32, BGRA>::new(2, 2);
+    canvas.fill_rect(&Vec2::new(0.0, 0.0), &Vec2::new(1.0, 1.0), &BGRA::from_vec4(Vec4::new(1.0, 0.5, 0.0, 1.0)), BlendMode::Override);
+    // the bytes of pixels as laid out in memory
+    let bytes: Vec<u8> = canvas.raw_pixels().iter().flat_map(|p| p.to_ne_bytes()).collect();
+    assert_eq!(&bytes[..4], &[0, 128, 255, 255]);
+    assert_eq!(ARGB { a: 1, r: 2, g: 3, b: 4 }.to_value().to_ne_bytes(), [1, 2, 3, 4]);
+
+    let mut canvas = BezierCanvas::<u16, RGB565>::new(2, 2);
+    canvas.fill_rect(&Vec2::new(0.0, 0.0), &Vec2::new(1.0, 1.0), &RGB565::from_vec4(Vec4::new(1.0, 0.0, 1.0, 1.0)), BlendMode::Override);
+    assert_eq!(canvas.raw_pixels()[0], 0xf81f);
+    canvas.fill_rect(&Vec2::new(0.0, 0.0), &Vec2::new(1.0, 1.0), &RGB565::from_vec4(Vec4::new(0.0, 1.0, 0.0, 1.0)), BlendMode::Screen);
+    assert_eq!(canvas.raw_pixels()[3], 0xffff);
+
+    let color = RGBA4444::from_vec4(Vec4::new(1.0, 0.0, 0.2, 0.6));
+    assert_eq!(color.to_value(), 0xf039);
+    assert_eq!(RGBA4444::from_value(0xf039), color);
+
+    // out of range channels saturate instead of spilling into their neighbours
+    assert_eq!(RGB565::from_vec4(Vec4::new(0.0, 2.0, 0.0, 1.0)).to_value(), 0x07e0);
+    assert_eq!(RGB565::from_vec4(Vec4::new(1.5, -0.5, 1.04, 1.0)).to_value(), 0xf81f);
+    assert_eq!(RGBA4444::from_vec4(Vec4::new(0.0, 1.2, -1.0, 3.0)).to_value(), 0x0f0f);
+}
+
+#[test]
+fn color_spaces() {
+    let close = |a: Vec4, b: Vec4, eps: f32| (a - b).norm() < eps;
+    let orange = RGB { r: 255, g: 128, b: 0 }.to_vec4();
+
+    let hsv = ColorSpace::HSV.from_rgb(orange);
+    assert!(close(hsv, Vec4::new(30.117647, 1.0, 1.0, 1.0), 1e-3));
+    let hsl = ColorSpace::HSL.from_rgb(Vec4::new(0.25, 0.5, 0.75, 1.0));
+    assert!(close(hsl, Vec4::new(210.0, 0.5, 0.5, 1.0), 1e-3));
+
+    let white = ColorSpace::Lab.from_rgb(Vec4::new(1.0, 1.0, 1.0, 0.5));
+    assert!(close(white, Vec4::new(100.0, 0.0, 0.0, 0.5), 1e-2));
+    let white = ColorSpace::OkLab.from_rgb(Vec4::new(1.0, 1.0, 1.0, 1.0));
+    assert!(close(white, Vec4::new(1.0, 0.0, 0.0, 1.0), 1e-3));
+
+    for space in [ColorSpace::SRGB, ColorSpace::LinearSRGB, ColorSpace::HSV, ColorSpace::HSL, ColorSpace::Lab, ColorSpace::LCh, ColorSpace::OkLab, ColorSpace::OkLCh] {
+        let color = Vec4::new(0.2, 0.6, 0.9, 0.75);
+        assert!(close(space.to_rgb(space.from_rgb(color)), color, 1e-3));
+        assert!(close(space.mix(orange, color, 0.0), orange, 1e-3));
+        assert!(close(space.mix(orange, color, 1.0), color, 1e-3));
+    }
+    // red to blue through the shorter hue arc passes magenta, not green
+    let mid = ColorSpace::HSV.mix(Vec4::new(1.0, 0.0, 0.0, 1.0), Vec4::new(0.0, 0.0, 1.0, 1.0), 0.5);
+    assert!(close(mid, Vec4::new(1.0, 0.0, 1.0, 1.0), 1e-3));
+
+    // reference pair from Sharma, Wu and Dalal
+    let lab0 = Vec4::new(50.0, 2.6772, -79.7751, 1.0);
+    let lab1 = Vec4::new(50.0, 0.0, -82.7485, 1.0);
+    assert!((delta_e2000(lab0, lab1) - 2.0425).abs() < 1e-3);
+    assert!((delta_e76(lab0, lab1) - 4.0011).abs() < 1e-3);
+}
+
+#[test]
+fn dithering() {
+    let mut canvas = BezierCanvas::<Vec4, RGBAF32>::new(32, 32);
+    canvas.fill_rect(&Vec2::new(0.0, 0.0), &Vec2::new(1.0, 1.0), &RGBAF32 { r: 0.31, g: 0.31, b: 0.31, a: 1.0 }, BlendMode::Override);
+    let mean = |canvas: &BezierCanvas<u16, RGBA4444>| {
+        let mut sum = 0.0;
+        for y in 0..canvas.height {
+            for x in 0..canvas.width {
+                sum += canvas.get_pixel(x, y).to_vec4().x();
+            }
+        }
+        sum / (canvas.width * canvas.height) as f32
+    };
+    // plain rounding bands to the nearest level
+    assert!((mean(&canvas.quantize(Dither::None)) - 5.0 / 15.0).abs() < 1e-6);
+    for dither in [Dither::Bayer, Dither::BlueNoise, Dither::FloydSteinberg] {
+        assert!((mean(&canvas.quantize(dither)) - 0.31).abs() < 0.005);
+    }
+
+    let mut thresholds: Vec<f32> = (0..32 * 32).map(|p| Dither::BlueNoise.threshold(p % 32, p / 32)).collect();
+    thresholds.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    thresholds.dedup();
+    assert_eq!(thresholds.len(), 32 * 32);
+
+    assert_eq!(Dither::Bayer.quantize::<u32, RGB>(Vec4::new(0.5 / 255.0, 0.0, 0.0, 1.0), 0, 0), RGB { r: 0, g: 0, b: 0 });
+    assert_eq!(Dither::Bayer.quantize::<u32, RGB>(Vec4::new(0.5 / 255.0, 0.0, 0.0, 1.0), 0, 3), RGB { r: 1, g: 0, b: 0 });
+
+    // empty canvases quantize to empty canvases
+    for dither in [Dither::None, Dither::Bayer, Dither::FloydSteinberg] {
+        let empty = BezierCanvas::<u32, RGBA>::new(0, 3).quantize::<u16, RGB565>(dither);
+        assert_eq!((empty.width, empty.height), (0, 3));
+        assert!(BezierCanvas::<u32, RGBA>::new(3, 0).quantize::<u16, RGB565>(dither).raw_pixels().is_empty());
+    }
+}
+
+#[test]
+fn indexed_png() {
+    let mut canvas = BezierCanvas::<u32, RGBA>::new(16, 16);
+    canvas.fill_rect(&Vec2::new(0.0, 0.0), &Vec2::new(0.5, 1.0), &RGBA { r: 255, g: 0, b: 0, a: 255 }, BlendMode::Override);
+    canvas.fill_rect(&Vec2::new(0.5, 0.0), &Vec2::new(0.5, 0.5), &RGBA { r: 0, g: 0, b: 255, a: 128 }, BlendMode::Override);
+
+    // fewer colors than the palette size are kept exactly
+    let palette = canvas.palette(16, PaletteMethod::MedianCut);
+    assert_eq!(palette.len(), 3);
+    canvas.export_indexed_png("target/debug/examples/indexed.png", &palette);
+    let loaded = BezierCanvas::<u32, RGBA>::from_png("target/debug/examples/indexed.png");
+    for (x, y) in [(0, 0), (15, 0), (15, 15)] {
+        assert_eq!(loaded.get_pixel(x, y), canvas.get_pixel(x, y));
+    }
+    // the palette has a tRNS chunk, whose alpha RGB canvases drop
+    let loaded = BezierCanvas::<u32, RGB>::from_png("target/debug/examples/indexed.png");
+    assert_eq!(loaded.get_pixel(0, 0), RGB { r: 255, g: 0, b: 0 });
+    assert_eq!(loaded.get_pixel(15, 0), RGB { r: 0, g: 0, b: 255 });
+
+    let texture = BezierCanvas::<u32, RGBA>::from_png("avatar.png");
+    let palette = texture.palette(2, PaletteMethod::KMeans(4));
+    assert_eq!(palette.len(), 2);
+    // palettes are capped to what an indexed PNG holds
+    let mut many = BezierCanvas::<u32, RGBA>::new(32, 32);
+    for (i, pixel) in many.raw_pixels_mut().iter_mut().enumerate() {
+        *pixel = RGBA { r: (i % 32 * 8) as u8, g: (i / 32 * 8) as u8, b: 0, a: 255 }.to_value();
+    }
+    let palette = many.palette(1000, PaletteMethod::MedianCut);
+    assert_eq!(palette.len(), 256);
+    many.export_indexed_png("target/debug/examples/many_indexed.png", &palette);
+    // and never empty
+    assert!(std::panic::catch_unwind(|| many.palette(0, PaletteMethod::MedianCut)).is_err());
+    assert!(std::panic::catch_unwind(|| many.map_to_palette(&[])).is_err());
+    let palette = texture.palette(32, PaletteMethod::KMeans(4));
+    texture.export_indexed_png("target/debug/examples/avatar_indexed.png", &palette);
+    let loaded = BezierCanvas::<u32, RGBA>::from_png("target/debug/examples/avatar_indexed.png");
+    let indices = texture.map_to_palette(&palette);
+    assert_eq!(loaded.get_pixel(10, 20), palette[indices[20 * texture.width + 10] as usize]);
+}
+
+#[test]
+fn layers() {
+    let red = RGBA { r: 255, g: 0, b: 0, a: 255 };
+    let mut canvas = BezierCanvas::<u32, RGBA>::new(16, 16);
+    canvas.fill_rect(&Vec2::new(0.0, 0.0), &Vec2::new(1.0, 1.0), &RGBA { r: 255, g: 255, b: 255, a: 255 }, BlendMode::Override);
+
+    // overlapping shapes of a group are not blended twice
+    canvas.push_layer(0.5, BlendMode::Alpha);
+    canvas.fill_rect(&Vec2::new(0.0, 0.0), &Vec2::new(0.75, 1.0), &red, BlendMode::Alpha);
+    canvas.fill_rect(&Vec2::new(0.25, 0.0), &Vec2::new(0.75, 1.0), &red, BlendMode::Alpha);
+    canvas.pop_layer();
+    assert_eq!(canvas.get_pixel(1, 8), RGBA { r: 255, g: 128, b: 128, a: 255 });
+    assert_eq!(canvas.get_pixel(8, 8), RGBA { r: 255, g: 128, b: 128, a: 255 });
+
+    // nested layers, with a blend mode applied to the group
+    canvas.push_layer(1.0, BlendMode::Multiply);
+    canvas.push_layer(1.0, BlendMode::Alpha);
+    canvas.fill_rect(&Vec2::new(0.0, 0.0), &Vec2::new(0.5, 1.0), &RGBA { r: 0, g: 255, b: 255, a: 255 }, BlendMode::Override);
+    canvas.pop_layer();
+    canvas.pop_layer();
+    assert_eq!(canvas.get_pixel(1, 8), RGBA { r: 0, g: 128, b: 128, a: 255 });
+    assert_eq!(canvas.get_pixel(14, 8), RGBA { r: 255, g: 128, b: 128, a: 255 });
+    canvas.export_png("target/debug/examples/layers.png");
+
+    // without alpha, a layer could not be transparent
+    let opaque = std::panic::catch_unwind(|| BezierCanvas::<u32, RGB>::new(4, 4).push_layer(1.0, BlendMode::Alpha));
+    assert!(opaque.is_err());
+}
+
+#[test]
+fn masks() {
+    let mut mask = BezierCanvas::<u8, A>::new(8, 8);
+    mask.fill_rect(&Vec2::new(0.0, 0.0), &Vec2::new(0.5, 1.0), &A { a: 255 }, BlendMode::Override);
+    mask.fill_rect(&Vec2::new(0.5, 0.0), &Vec2::new(0.5, 0.5), &A { a: 128 }, BlendMode::Override);
+
+    let mut canvas = BezierCanvas::<u32, RGB>::new(16, 16);
+    canvas.fill_rect(&Vec2::new(0.0, 0.0), &Vec2::new(1.0, 1.0), &RGB { r: 255, g: 255, b: 255 }, BlendMode::Override);
+    canvas.set_mask(&mask, MaskMode::Alpha);
+    canvas.fill_rect(&Vec2::new(0.0, 0.0), &Vec2::new(1.0, 1.0), &RGB { r: 255, g: 0, b: 0 }, BlendMode::Override);
+    assert_eq!(canvas.get_pixel(2, 2), RGB { r: 255, g: 0, b: 0 });
+    assert_eq!(canvas.get_pixel(12, 2), RGB { r: 255, g: 127, b: 127 });
+    assert_eq!(canvas.get_pixel(12, 12), RGB { r: 255, g: 255, b: 255 });
+    canvas.stroke_line(&Vec2::new(0.0, 0.9), &Vec2::new(1.0, 0.9), &RGB { r: 0, g: 0, b: 0 }, BlendMode::Override);
+    assert_eq!(canvas.get_pixel(2, 14), RGB { r: 0, g: 0, b: 0 });
+    assert_eq!(canvas.get_pixel(12, 14), RGB { r: 255, g: 255, b: 255 });
+
+    let mut luminance = BezierCanvas::<u8, R>::new(1, 1);
+    luminance.fill_rect(&Vec2::new(0.0, 0.0), &Vec2::new(1.0, 1.0), &R { r: 51 }, BlendMode::Override);
+    canvas.set_mask(&luminance, MaskMode::Luminance);
+    canvas.fill_circle(&Vec2::new(0.75, 0.75), 0.1, &RGB { r: 0, g: 0, b: 0 }, BlendMode::Override);
+    assert_eq!(canvas.get_pixel(12, 12), RGB { r: 204, g: 204, b: 204 });
+    canvas.clear_mask();
+    canvas.fill_circle(&Vec2::new(0.75, 0.75), 0.1, &RGB { r: 0, g: 0, b: 0 }, BlendMode::Override);
+    assert_eq!(canvas.get_pixel(12, 12), RGB { r: 0, g: 0, b: 0 });
+
+    // an empty canvas takes any mask, but an empty mask has no pixel to stretch
+    BezierCanvas::<u32, RGB>::new(0, 4).set_mask(&mask, MaskMode::Alpha);
+    let empty = std::panic::catch_unwind(|| BezierCanvas::<u32, RGB>::new(4, 4).set_mask(&BezierCanvas::<u8, A>::new(0, 0), MaskMode::Alpha));
+    assert!(empty.is_err());
+}
+
+#[test]
+fn draw_image() {
+    let mut src = BezierCanvas::<u32, RGBA>::new(2, 2);
+    src.raw_pixels_mut()[0] = RGBA { r: 255, g: 0, b: 0, a: 255 }.to_value();
+    src.raw_pixels_mut()[3] = RGBA { r: 0, g: 0, b: 255, a: 255 }.to_value();
+
+    let mut canvas = BezierCanvas::<u32, RGB>::new(16, 16);
+    canvas.fill_rect(&Vec2::new(0.0, 0.0), &Vec2::new(1.0, 1.0), &RGB { r: 255, g: 255, b: 255 }, BlendMode::Override);
+    canvas.draw_image_rect::<u32, RGBA, NearestFilter>(&src, &Vec2::new(0.5, 0.5), &Vec2::new(0.5, 0.5), BlendMode::Alpha, 1.0);
+    assert_eq!(canvas.get_pixel(4, 4), RGB { r: 255, g: 255, b: 255 });
+    assert_eq!(canvas.get_pixel(9, 9), RGB { r: 255, g: 0, b: 0 });
+    // transparent source pixels leave the destination untouched
+    assert_eq!(canvas.get_pixel(14, 9), RGB { r: 255, g: 255, b: 255 });
+    assert_eq!(canvas.get_pixel(14, 14), RGB { r: 0, g: 0, b: 255 });
+
+    // mirrored horizontally over the left half, at half opacity
+    let transform = BMatrix { v: [[-0.5, 0.0, 0.5], [0.0, 1.0, 0.0]] };
+    canvas.draw_image::<u32, RGBA, NearestFilter>(&src, &transform, BlendMode::Alpha, 0.5);
+    assert_eq!(canvas.get_pixel(6, 2), RGB { r: 255, g: 128, b: 128 });
+    assert_eq!(canvas.get_pixel(1, 2), RGB { r: 255, g: 255, b: 255 });
+    assert_eq!(canvas.get_pixel(1, 14), RGB { r: 128, g: 128, b: 255 });
+
+    let texture = BezierCanvas::<u32, RGB>::from_png("avatar.png");
+    canvas.draw_image_rect::<u32, RGB, LinearFilter>(&texture, &Vec2::new(0.25, 0.25), &Vec2::new(0.5, 0.5), BlendMode::Override, 1.0);
+    canvas.export_png("target/debug/examples/draw_image.png");
+}
+
+#[test]
+fn resize() {
+    // a 1px checkerboard averages to gray when halved with any kernel
+    let mut checker = BezierCanvas::<u32, RGB>::new(8, 8);
+    for (i, pixel) in checker.raw_pixels_mut().iter_mut().enumerate() {
+        if (i % 8 + i / 8) % 2 == 0 {
+            *pixel = RGB { r: 255, g: 255, b: 255 }.to_value();
+        }
+    }
+    for kernel in [ResizeKernel::Box, ResizeKernel::Bilinear, ResizeKernel::Bicubic, ResizeKernel::Mitchell, ResizeKernel::Lanczos3] {
+        let small = checker.resize(4, 4, kernel);
+        assert_eq!((small.width, small.height), (4, 4));
+        let gray = small.get_pixel(1, 2);
+        assert!((gray.r as i32 - 128).abs() <= 1, "{:?}: {:?}", kernel, gray);
+    }
+
+    // transparent pixels do not bleed their color
+    let mut edge = BezierCanvas::<u32, RGBA>::new(2, 1);
+    edge.raw_pixels_mut()[0] = RGBA { r: 255, g: 0, b: 0, a: 255 }.to_value();
+    let wide = edge.resize(4, 1, ResizeKernel::Bilinear);
+    assert_eq!(wide.get_pixel(0, 0), RGBA { r: 255, g: 0, b: 0, a: 255 });
+    let mid = wide.get_pixel(2, 0);
+    assert_eq!((mid.r, mid.g, mid.b), (255, 0, 0));
+    assert_eq!(mid.a, 64);
+
+    let avatar = BezierCanvas::<u32, RGB>::from_png("avatar.png");
+    avatar.resize(avatar.width / 3, avatar.height / 3, ResizeKernel::Lanczos3).export_png("target/debug/examples/resize.png");
+
+    // empty canvases on either side
+    let empty = avatar.resize(0, 5, ResizeKernel::Bicubic);
+    assert_eq!((empty.width, empty.height), (0, 5));
+    assert_eq!(empty.resize(3, 3, ResizeKernel::Bicubic).get_pixel(1, 1), RGB { r: 0, g: 0, b: 0 });
+}
+
+#[test]
+fn blur() {
+    // a single white pixel spreads symmetrically and keeps its energy
+    let mut canvas = BezierCanvas::<Vec4, RGBAF32>::new(21, 21);
+    canvas.raw_pixels_mut()[10 * 21 + 10] = Vec4::new(1.0, 1.0, 1.0, 1.0);
+    canvas.gaussian_blur(2.0);
+    let total: f32 = canvas.raw_pixels().iter().map(|p| p.w()).sum();
+    assert!((total - 1.0).abs() < 1e-4);
+    assert_eq!(canvas.get_pixel(8, 10).a, canvas.get_pixel(12, 10).a);
+    assert_eq!(canvas.get_pixel(10, 8).a, canvas.get_pixel(8, 10).a);
+    assert!(canvas.get_pixel(10, 10).a > canvas.get_pixel(11, 10).a);
+    // premultiplied, so the color of the spread pixel stays white
+    assert!((canvas.get_pixel(12, 11).r - 1.0).abs() < 1e-4);
+
+    // box blur only touches the region, and flat areas stay flat
+    let mut canvas = BezierCanvas::<u32, RGB>::new(16, 16);
+    canvas.fill_rect(&Vec2::new(0.0, 0.0), &Vec2::new(0.5, 1.0), &RGB { r: 255, g: 255, b: 255 }, BlendMode::Override);
+    canvas.box_blur_rect(&Vec2::new(0.0, 0.0), &Vec2::new(1.0, 0.5), 2, 3);
+    assert_eq!(canvas.get_pixel(1, 4), RGB { r: 255, g: 255, b: 255 });
+    assert_eq!(canvas.get_pixel(15, 4), RGB { r: 0, g: 0, b: 0 });
+    let edge = canvas.get_pixel(8, 4);
+    assert!(edge.r > 0 && edge.r < 255);
+    assert_eq!(canvas.get_pixel(9, 12), RGB { r: 0, g: 0, b: 0 });
+
+    // the shadow shows below and right of the shape, but not through it
+    let mut canvas = BezierCanvas::<u32, RGBA>::new(32, 32);
+    canvas.fill_rect(&Vec2::new(0.25, 0.25), &Vec2::new(0.25, 0.25), &RGBA { r: 255, g: 0, b: 0, a: 255 }, BlendMode::Override);
+    canvas.drop_shadow(4, 4, 1.0, &RGBA { r: 0, g: 0, b: 0, a: 128 });
+    assert_eq!(canvas.get_pixel(10, 10), RGBA { r: 255, g: 0, b: 0, a: 255 });
+    assert!((canvas.get_pixel(18, 18).a as i32 - 128).abs() <= 1);
+    assert_eq!(canvas.get_pixel(4, 4).a, 0);
+    assert_eq!(canvas.get_pixel(2, 20).a, 0);
+    canvas.export_png("target/debug/examples/drop_shadow.png");
+
+    // nothing to blur on an empty canvas
+    let mut empty = BezierCanvas::<u32, RGBA>::new(0, 4);
+    empty.gaussian_blur(2.0);
+    empty.box_blur(2, 3);
+    empty.drop_shadow(1, 1, 1.0, &RGBA { r: 0, g: 0, b: 0, a: 128 });
+}
+
+#[test]
+fn convolution() {
+    // a white vertical bar on black
+    let mut canvas = BezierCanvas::<u32, RGB>::new(8, 8);
+    for (i, pixel) in canvas.raw_pixels_mut().iter_mut().enumerate() {
+        if i % 8 == 3 {
+            *pixel = RGB { r: 255, g: 255, b: 255 }.to_value();
+        }
+    }
+
+    // shifting kernel, wrapped around or clamped at the edge
+    let shift = Kernel::new(3, 1, vec![0.0, 0.0, 1.0]);
+    let shifted = canvas.convolve::<WrapRepeat, WrapRepeat>(&shift, false);
+    assert_eq!(shifted.get_pixel(2, 5), RGB { r: 255, g: 255, b: 255 });
+    assert_eq!(shifted.get_pixel(3, 5), RGB { r: 0, g: 0, b: 0 });
+    let shifted = canvas.convolve::<WrapRepeat, WrapRepeat>(&Kernel::new(3, 1, vec![1.0, 0.0, 0.0]), false);
+    assert_eq!(shifted.get_pixel(4, 0), RGB { r: 255, g: 255, b: 255 });
+    let mut edge = BezierCanvas::<u32, RGB>::new(2, 1);
+    edge.raw_pixels_mut()[1] = RGB { r: 255, g: 0, b: 0 }.to_value();
+    assert_eq!(edge.convolve::<WrapRepeat, WrapClampToEdge>(&shift, false).get_pixel(1, 0), RGB { r: 0, g: 0, b: 0 });
+    assert_eq!(edge.convolve::<WrapClampToEdge, WrapClampToEdge>(&shift, false).get_pixel(1, 0), RGB { r: 255, g: 0, b: 0 });
+
+    // wrappings defining only wrap get texel indices from it
+    struct WrapClampOnly {}
+    impl Wrapping for WrapClampOnly {
+        fn wrap(x: f32) -> f32 {
+            x.clamp(0.0, 1.0)
+        }
+    }
+    assert_eq!(WrapClampOnly::wrap_index(-2, 8), 0);
+    assert_eq!(WrapClampOnly::wrap_index(5, 8), 5);
+    assert_eq!(WrapClampOnly::wrap_index(9, 8), 7);
+    assert_eq!(WrapClampOnly::wrap_index(3, 1), 0);
+    assert_eq!(edge.convolve::<WrapClampOnly, WrapClampOnly>(&shift, false).get_pixel(1, 0), RGB { r: 255, g: 0, b: 0 });
+
+    // edges respond only next to the bar, flat areas are unchanged or zero
+    let edges = canvas.edge_detect(EdgeOperator::Sobel);
+    assert_eq!(edges.get_pixel(0, 4), RGB { r: 0, g: 0, b: 0 });
+    assert_eq!(edges.get_pixel(2, 4), RGB { r: 255, g: 255, b: 255 });
+    assert_eq!(edges.get_pixel(3, 4), RGB { r: 0, g: 0, b: 0 });
+    assert_eq!(canvas.edge_detect(EdgeOperator::Prewitt).get_pixel(4, 4), RGB { r: 255, g: 255, b: 255 });
+    let laplacian = canvas.laplacian();
+    assert_eq!(laplacian.get_pixel(3, 4), RGB { r: 255, g: 255, b: 255 });
+    assert_eq!(laplacian.get_pixel(6, 4), RGB { r: 0, g: 0, b: 0 });
+
+    let mut gray = BezierCanvas::<u32, RGB>::new(4, 4);
+    gray.fill_rect(&Vec2::new(0.0, 0.0), &Vec2::new(1.0, 1.0), &RGB { r: 100, g: 100, b: 100 }, BlendMode::Override);
+    gray.raw_pixels_mut()[5] = RGB { r: 120, g: 120, b: 120 }.to_value();
+    assert_eq!(gray.sharpen().get_pixel(1, 1), RGB { r: 200, g: 200, b: 200 });
+    assert_eq!(gray.sharpen().get_pixel(3, 3), RGB { r: 100, g: 100, b: 100 });
+    assert_eq!(gray.emboss().get_pixel(3, 3), RGB { r: 100, g: 100, b: 100 });
+    assert!(gray.unsharp_mask(1.0, 1.0).get_pixel(1, 1).r > 120);
+    assert!(gray.unsharp_mask(1.0, 1.0).get_pixel(2, 1).r < 100);
+
+    // empty canvases stay empty
+    for empty in [BezierCanvas::<u32, RGB>::new(0, 3), BezierCanvas::<u32, RGB>::new(3, 0)] {
+        assert!(empty.convolve::<WrapRepeat, WrapClampToEdge>(&shift, false).raw_pixels().is_empty());
+        assert!(empty.sharpen().raw_pixels().is_empty());
+        assert!(empty.emboss().raw_pixels().is_empty());
+        assert!(empty.laplacian().raw_pixels().is_empty());
+        assert!(empty.unsharp_mask(1.0, 1.0).raw_pixels().is_empty());
+        assert!(empty.edge_detect(EdgeOperator::Sobel).raw_pixels().is_empty());
+    }
+
+    BezierCanvas::<u32, RGB>::from_png("avatar.png").edge_detect(EdgeOperator::Sobel).export_png("target/debug/examples/sobel.png");
+}
+
+#[test]
+fn color_matrix() {
+    let orange = RGBA { r: 255, g: 128, b: 0, a: 200 };
+    let mut canvas = BezierCanvas::<u32, RGBA>::new(2, 2);
+    canvas.fill_rect(&Vec2::new(0.0, 0.0), &Vec2::new(1.0, 1.0), &orange, BlendMode::Override);
+
+    canvas.color_matrix(&ColorMatrix::invert());
+    assert_eq!(canvas.get_pixel(0, 0), RGBA { r: 0, g: 127, b: 255, a: 200 });
+    canvas.color_matrix(&ColorMatrix::invert());
+    assert_eq!(canvas.get_pixel(0, 0), orange);
+
+    canvas.color_matrix(&ColorMatrix::grayscale(1.0));
+    let gray = canvas.get_pixel(1, 1);
+    assert_eq!((gray.r, gray.g, gray.b, gray.a), (146, 146, 146, 200));
+
+    // composition matches applying one after the other
+    let composed = ColorMatrix::sepia(1.0).then(&ColorMatrix::hue_rotate(90.0));
+    let color = Vec4::new(0.2, 0.4, 0.6, 1.0);
+    let twice = ColorMatrix::hue_rotate(90.0).apply(ColorMatrix::sepia(1.0).apply(color));
+    assert!((composed.apply(color) - twice).v.iter().all(|d| d.abs() < 1e-6));
+    // a full turn and no saturation change are identities
+    assert!((ColorMatrix::hue_rotate(360.0).apply(color) - color).v.iter().all(|d| d.abs() < 1e-5));
+    assert_eq!(ColorMatrix::saturate(1.0).apply(color), color);
+    assert_eq!(ColorMatrix::brightness_contrast(1.0, 1.0).apply(color), color);
+
+    let mut canvas = BezierCanvas::<u32, RGB>::new(1, 1);
+    canvas.fill_rect(&Vec2::new(0.0, 0.0), &Vec2::new(1.0, 1.0), &RGB { r: 64, g: 128, b: 255 }, BlendMode::Override);
+    canvas.component_transfer(&[
+        Transfer::Linear { slope: 2.0, intercept: 0.0 },
+        Transfer::Table(vec![1.0, 0.0]),
+        Transfer::Gamma { amplitude: 1.0, exponent: 2.0, offset: 0.0 },
+        Transfer::Identity
+    ]);
+    assert_eq!(canvas.get_pixel(0, 0), RGB { r: 128, g: 127, b: 255 });
+
+    let mut avatar = BezierCanvas::<u32, RGB>::from_png("avatar.png");
+    avatar.color_matrix(&ColorMatrix::sepia(1.0));
+    avatar.export_png("target/debug/examples/sepia.png");
+}
+
+#[test]
+fn morphology() {
+    let mut dot = BezierCanvas::<u8, A>::new(16, 16);
+    dot.raw_pixels_mut()[8 * 16 + 8] = 255;
+    dot.dilate(StructuringElement::Disc(3));
+    assert_eq!(dot.get_pixel(11, 8).a, 255);
+    assert_eq!(dot.get_pixel(8, 5).a, 255);
+    assert_eq!(dot.get_pixel(10, 10).a, 255);
+    assert_eq!(dot.get_pixel(11, 11).a, 0);
+    assert_eq!(dot.get_pixel(12, 8).a, 0);
+    // eroding the dilated dot (a closing) gives back the single pixel
+    dot.erode(StructuringElement::Disc(3));
+    assert_eq!(dot.raw_pixels().iter().filter(|a| **a != 0).count(), 1);
+    // which does not survive opening
+    dot.open(StructuringElement::Rect(1, 1));
+    assert!(dot.raw_pixels().iter().all(|a| *a == 0));
+
+    // empty canvases are left alone
+    for mut empty in [BezierCanvas::<u8, A>::new(0, 3), BezierCanvas::<u8, A>::new(3, 0)] {
+        empty.dilate(StructuringElement::Rect(1, 1));
+        empty.erode(StructuringElement::Disc(2));
+        empty.open(StructuringElement::Disc(1));
+        empty.close(StructuringElement::Rect(2, 1));
+        assert!(empty.raw_pixels().is_empty());
+    }
+
+    // matches a brute force maximum for radii larger than the canvas too
+    let mut noise = BezierCanvas::<u8, A>::new(13, 7);
+    let mut seed = 7u32;
+    for pixel in noise.raw_pixels_mut() {
+        seed = seed.wrapping_mul(1664525).wrapping_add(1013904223);
+        *pixel = (seed >> 24) as u8;
+    }
+    for (rx, ry) in [(1, 2), (4, 0), (20, 3)] {
+        let mut dilated = BezierCanvas::<u8, A>::new(13, 7);
+        dilated.raw_pixels_mut().copy_from_slice(noise.raw_pixels());
+        dilated.dilate(StructuringElement::Rect(rx, ry));
+        for y in 0..7usize {
+            for x in 0..13usize {
+                let expected = (y.saturating_sub(ry)..(y + ry + 1).min(7))
+                    .flat_map(|sy| (x.saturating_sub(rx)..(x + rx + 1).min(13)).map(move |sx| (sx, sy)))
+                    .map(|(sx, sy)| noise.get_pixel(sx, sy).a)
+                    .max()
+                    .unwrap();
+                assert_eq!(dilated.get_pixel(x, y).a, expected);
+            }
+        }
+    }
+
+    // closing fills a hole in a ring
+    let mut ring = BezierCanvas::<u8, R>::new(9, 9);
+    ring.fill_rect(&Vec2::new(0.3, 0.3), &Vec2::new(0.4, 0.4), &R { r: 255 }, BlendMode::Override);
+    ring.raw_pixels_mut()[4 * 9 + 4] = 0;
+    ring.close(StructuringElement::Rect(1, 1));
+    assert_eq!(ring.get_pixel(4, 4).r, 255);
+    assert_eq!(ring.get_pixel(0, 0).r, 0);
+}
+
+#[test]
+fn geometry() {
+    // 3x2 canvas with distinct pixels
+    let mut canvas = BezierCanvas::<u8, R>::new(3, 2);
+    canvas.raw_pixels_mut().copy_from_slice(&[1, 2, 3, 4, 5, 6]);
+
+    assert_eq!(canvas.crop(1, 0, 2, 2).raw_pixels(), &[2, 3, 5, 6]);
+    let clipped = canvas.crop(2, 1, 5, 5);
+    assert_eq!((clipped.width, clipped.height), (1, 1));
+    assert_eq!(clipped.raw_pixels(), &[6]);
+
+    let padded = canvas.pad(1, 0, 0, 1, &R { r: 9 });
+    assert_eq!((padded.width, padded.height), (4, 3));
+    assert_eq!(padded.raw_pixels(), &[9, 1, 2, 3, 9, 4, 5, 6, 9, 9, 9, 9]);
+    let padded = canvas.crop(0, 0, 0, 2).pad(0, 1, 0, 1, &R { r: 9 });
+    assert_eq!((padded.width, padded.height), (0, 4));
+
+    assert_eq!(canvas.flip_horizontal().raw_pixels(), &[3, 2, 1, 6, 5, 4]);
+    assert_eq!(canvas.flip_vertical().raw_pixels(), &[4, 5, 6, 1, 2, 3]);
+    assert_eq!(canvas.rotate_180().raw_pixels(), &[6, 5, 4, 3, 2, 1]);
+    let rotated = canvas.rotate_90();
+    assert_eq!((rotated.width, rotated.height), (2, 3));
+    assert_eq!(rotated.raw_pixels(), &[4, 1, 5, 2, 6, 3]);
+    assert_eq!(canvas.rotate_270().raw_pixels(), &[3, 6, 2, 5, 1, 4]);
+    assert_eq!(canvas.transpose().raw_pixels(), &[1, 4, 2, 5, 3, 6]);
+    assert_eq!(canvas.rotate_90().rotate_270().raw_pixels(), canvas.raw_pixels());
+}
+
+#[test]
+fn views() {
+    let red = RGB { r: 255, g: 0, b: 0 };
+    let blue = RGB { r: 0, g: 0, b: 255 };
+    let white = RGB { r: 255, g: 255, b: 255 };
+    let curve = [Vec2::new(0.0, 0.0), Vec2::new(0.5, 1.5), Vec2::new(1.2, 0.2)];
+    let triangle = vec![vec![Vec2::new(0.5, 0.05), Vec2::new(0.95, 0.3), Vec2::new(0.6, 0.45)]];
+
+    // drawing into a view is the same as drawing onto a canvas of its size
+    let mut expected = BezierCanvas::<u32, RGB>::new(16, 12);
+    expected.fill_rect(&Vec2::new(0.0, 0.0), &Vec2::new(1.0, 1.0), &white, BlendMode::Override);
+    expected.fill_rect(&Vec2::new(0.1, 0.1), &Vec2::new(0.3, 0.6), &red, BlendMode::Override);
+    expected.fill_circle(&Vec2::new(0.9, 0.5), 0.3, &blue, BlendMode::Override);
+    expected.stroke_bezier::<3>(&curve, &red, 50, BlendMode::Override);
+    expected.fill_shape(&triangle, &blue, BlendMode::Override);
+
+    let mut canvas = BezierCanvas::<u32, RGB>::new(40, 30);
+    canvas.fill_rect(&Vec2::new(0.0, 0.0), &Vec2::new(1.0, 1.0), &white, BlendMode::Override);
+    let mut view = canvas.view_mut(20, 8, 16, 12);
+    view.fill_rect(&Vec2::new(0.1, 0.1), &Vec2::new(0.3, 0.6), &red, BlendMode::Override);
+    // sticks out of the view
+    view.fill_circle(&Vec2::new(0.9, 0.5), 0.3, &blue, BlendMode::Override);
+    view.stroke_bezier::<3>(&curve, &red, 50, BlendMode::Override);
+    view.fill_shape(&triangle, &blue, BlendMode::Override);
+    assert_eq!(view.get_pixel(15, 6), expected.get_pixel(15, 6));
+
+    canvas.export_png("target/debug/examples/view.png");
+    for y in 0..30 {
+        for x in 0..40 {
+            let expected = if (20..36).contains(&x) && (8..20).contains(&y) { expected.get_pixel(x - 20, y - 8) } else { white };
+            assert_eq!(canvas.get_pixel(x, y), expected, "({}, {})", x, y);
+        }
+    }
+
+    // images are mapped through the view too, and views are clipped to the canvas
+    let mut src = BezierCanvas::<u32, RGB>::new(1, 1);
+    src.raw_pixels_mut()[0] = red.to_value();
+    let mut view = canvas.view_mut(30, 0, 20, 20);
+    assert_eq!((view.width, view.height), (10, 20));
+    view.draw_image_rect::<u32, RGB, NearestFilter>(&src, &Vec2::new(0.5, 0.0), &Vec2::new(0.5, 0.1), BlendMode::Override, 1.0);
+    assert_eq!(canvas.get_pixel(35, 1), red);
+    assert_eq!(canvas.get_pixel(34, 1), white);
+    assert_eq!(canvas.get_pixel(35, 2), white);
+
+    // rows of a view are the rect of the canvas buffer, `canvas.width` pixels apart
+    let mut view = canvas.view_mut(2, 3, 4, 2);
+    view.raw_row_mut(1).fill(blue.to_value());
+    assert_eq!(view.raw_row(0).len(), 4);
+    assert_eq!(view.get_pixel(3, 1), blue);
+    assert_eq!(canvas.get_pixel(1, 4), white);
+    assert_eq!(canvas.get_pixel(2, 4), blue);
+    assert_eq!(canvas.get_pixel(5, 4), blue);
+    assert_eq!(canvas.get_pixel(6, 4), white);
+    assert_eq!(canvas.get_pixel(2, 3), white);
+
+    // the mask of the canvas covers views of it
+    let mut mask = BezierCanvas::<u8, A>::new(2, 1);
+    mask.raw_pixels_mut()[0] = 255;
+    canvas.set_mask(&mask, MaskMode::Alpha);
+    canvas.view_mut(10, 0, 20, 2).fill_rect(&Vec2::new(0.0, 0.0), &Vec2::new(1.0, 1.0), &red, BlendMode::Override);
+    assert_eq!(canvas.get_pixel(19, 1), red);
+    assert_eq!(canvas.get_pixel(20, 1), white);
+}
+
+#[test]
+fn conversion() {
+    let mut canvas = BezierCanvas::<u32, RGBA>::new(2, 1);
+    canvas.raw_pixels_mut()[0] = RGBA { r: 0, g: 255, b: 0, a: 255 }.to_value();
+    canvas.raw_pixels_mut()[1] = RGBA { r: 255, g: 0, b: 0, a: 128 }.to_value();
+
+    let rgb = canvas.convert::<u32, RGB>();
+    assert_eq!(rgb.get_pixel(1, 0), RGB { r: 255, g: 0, b: 0 });
+    assert_eq!(rgb.convert::<u32, RGBA>().get_pixel(0, 0), RGBA { r: 0, g: 255, b: 0, a: 255 });
+    assert_eq!(canvas.convert::<u8, R>().get_pixel(0, 0), R { r: 85 });
+    assert_eq!(canvas.convert::<u64, RGBA16>().get_pixel(1, 0), RGBA16 { r: 65535, g: 0, b: 0, a: 32896 });
+
+    let flattened = canvas.convert_with::<u32, RGB>(Conversion { background: Some(Vec4::new(1.0, 1.0, 1.0, 1.0)), ..Default::default() });
+    assert_eq!(flattened.get_pixel(1, 0), RGB { r: 255, g: 127, b: 127 });
+    let gray = canvas.convert_with::<u8, R>(Conversion { grayscale: Some(Luma::Rec709), ..Default::default() });
+    assert_eq!(gray.get_pixel(0, 0), R { r: 182 });
+    let gray = canvas.convert_with::<u16, RA>(Conversion { grayscale: Some(Luma::Rec601), ..Default::default() });
+    assert_eq!(gray.get_pixel(1, 0), RA { r: 76, a: 128 });
+}
+
+#[test]
+fn comparison() {
+    let avatar = BezierCanvas::<u32, RGB>::from_png("avatar.png").crop(150, 150, 96, 96);
+    let same = avatar.compare(&avatar);
+    assert_eq!((same.max_error, same.mean_error, same.psnr, same.ssim), (0.0, 0.0, f32::INFINITY, 1.0));
+
+    let mut brighter = avatar.convert::<u32, RGB>();
+    brighter.fill_rect(&Vec2::new(0.0, 0.0), &Vec2::new(1.0, 1.0), &RGB { r: 4, g: 4, b: 4 }, BlendMode::Screen);
+    let close = avatar.compare(&brighter);
+    assert!(close.max_error > 0.0 && close.max_error < 5.0 / 255.0);
+    assert!(close.psnr > 35.0 && close.ssim > 0.95);
+    let mut blurred = avatar.convert::<u32, RGB>();
+    blurred.gaussian_blur(3.0);
+    let far = avatar.compare(&blurred);
+    assert!(far.psnr < close.psnr && far.ssim < close.ssim && far.mean_error > close.mean_error);
+
+    let mut canvas = BezierCanvas::<u32, RGBA>::new(2, 1);
+    canvas.raw_pixels_mut()[1] = RGBA { r: 255, g: 0, b: 0, a: 255 }.to_value();
+    let diff = canvas.diff(&BezierCanvas::new(2, 1));
+    assert_eq!(diff.get_pixel(0, 0), RGBA { r: 0, g: 0, b: 0, a: 255 });
+    assert_eq!(diff.get_pixel(1, 0), RGBA { r: 255, g: 255, b: 255, a: 255 });
+
+    let golden = "target/debug/examples/golden.png";
+    avatar.export_png(golden);
+    assert_canvas_matches(&brighter, golden, 5.0 / 255.0);
+    let failed = std::panic::catch_unwind(|| assert_canvas_matches(&blurred, golden, 4.0 / 255.0));
+    assert!(failed.is_err());
+    assert!(std::path::Path::new("target/debug/examples/golden.diff.png").exists());
+}
+
+#[test]
+fn histogram() {
+    // a dull gradient from 64 to 191
+    let mut canvas = BezierCanvas::<u8, R>::new(128, 1);
+    for (i, pixel) in canvas.raw_pixels_mut().iter_mut().enumerate() {
+        *pixel = 64 + i as u8;
+    }
+    let histogram = canvas.histogram(256);
+    assert_eq!(histogram.bins(), 256);
+    assert_eq!(histogram.channels[0][64], 1);
+    assert_eq!(histogram.channels[0][63], 0);
+    assert_eq!(histogram.luma[100], 1);
+    assert_eq!(histogram.channels[3][255], 128);
+    assert_eq!(canvas.histogram(2).luma, vec![64, 64]);
+
+    let mut stretched = canvas.convert::<u8, R>();
+    stretched.auto_contrast(0.0);
+    assert_eq!(stretched.get_pixel(0, 0), R { r: 0 });
+    assert_eq!(stretched.get_pixel(127, 0), R { r: 255 });
+    let mut equalized = canvas.convert::<u8, R>();
+    equalized.equalize();
+    assert_eq!(equalized.get_pixel(127, 0), R { r: 255 });
+    assert!((equalized.get_pixel(64, 0).r as i32 - 129).abs() <= 2);
+
+    // per channel stretching removes a color cast, contrast stretching keeps it
+    let mut cast = BezierCanvas::<u32, RGB>::new(2, 1);
+    cast.raw_pixels_mut()[0] = RGB { r: 50, g: 0, b: 0 }.to_value();
+    cast.raw_pixels_mut()[1] = RGB { r: 250, g: 200, b: 200 }.to_value();
+    let mut levels = cast.convert::<u32, RGB>();
+    levels.auto_levels(0.0);
+    assert_eq!(levels.get_pixel(0, 0), RGB { r: 0, g: 0, b: 0 });
+    assert_eq!(levels.get_pixel(1, 0), RGB { r: 255, g: 255, b: 255 });
+    let mut contrast = cast.convert::<u32, RGB>();
+    contrast.auto_contrast(0.0);
+    assert_eq!(contrast.get_pixel(0, 0), RGB { r: 51, g: 0, b: 0 });
+    assert_eq!(contrast.get_pixel(1, 0), RGB { r: 255, g: 204, b: 204 });
+
+    // the curve passes through its points and never overshoots
+    let curve = Curve::new(&[Vec2::new(0.0, 0.0), Vec2::new(0.25, 0.5), Vec2::new(0.5, 0.55), Vec2::new(1.0, 1.0)]);
+    assert!((curve.eval(0.25) - 0.5).abs() < 1e-6);
+    assert_eq!(curve.eval(-1.0), 0.0);
+    let samples: Vec<f32> = (0..=100).map(|i| curve.eval(i as f32 / 100.0)).collect();
+    assert!(samples.windows(2).all(|w| w[1] >= w[0]));
+    assert_eq!(Curve::new(&[]).eval(0.3), 0.3);
+    let mut curved = canvas.convert::<u8, R>();
+    curved.curves(&curve);
+    assert_eq!(curved.get_pixel(0, 0), R { r: 128 });
+    let mut transferred = canvas.convert::<u8, R>();
+    transferred.component_transfer(&[Transfer::Curve(curve.clone()), Transfer::Curve(curve.clone()), Transfer::Curve(curve), Transfer::Identity]);
+    assert_eq!(transferred.raw_pixels(), curved.raw_pixels());
+}
+
+#[test]
+fn flood_fill() {
+    let white = RGB { r: 255, g: 255, b: 255 };
+    let black = RGB { r: 0, g: 0, b: 0 };
+    let red = RGB { r: 255, g: 0, b: 0 };
+    // white canvas split by a black diagonal, which 4-connected fills cannot cross
+    let mut canvas = BezierCanvas::<u32, RGB>::new(8, 8);
+    for (i, pixel) in canvas.raw_pixels_mut().iter_mut().enumerate() {
+        *pixel = if i % 8 == i / 8 { black } else { white }.to_value();
+    }
+    let region = canvas.flood_select(7, 0, 0.0, Connectivity::Four);
+    assert_eq!(region.raw_pixels().iter().filter(|a| **a == 255).count(), 28);
+    assert_eq!(region.get_pixel(0, 7).a, 0);
+    assert_eq!(canvas.flood_select(7, 0, 0.0, Connectivity::Eight).get_pixel(0, 7).a, 255);
+    // the diagonal itself is connected only diagonally
+    assert_eq!(canvas.flood_select(0, 0, 0.0, Connectivity::Four).raw_pixels().iter().filter(|a| **a == 255).count(), 1);
+    assert_eq!(canvas.flood_select(0, 0, 0.0, Connectivity::Eight).raw_pixels().iter().filter(|a| **a == 255).count(), 8);
+
+    canvas.flood_fill(7, 0, 0.0, Connectivity::Four, &red, BlendMode::Override);
+    assert_eq!(canvas.get_pixel(6, 1), red);
+    assert_eq!(canvas.get_pixel(1, 1), black);
+    assert_eq!(canvas.get_pixel(1, 6), white);
+
+    // tolerance of a gradient around a hole
+    let mut gradient = BezierCanvas::<u8, R>::new(10, 3);
+    for (i, pixel) in gradient.raw_pixels_mut().iter_mut().enumerate() {
+        *pixel = (i % 10) as u8 * 10;
+    }
+    gradient.raw_pixels_mut()[10 + 2] = 255;
+    let region = gradient.flood_select(0, 1, 40.5 / 255.0, Connectivity::Four);
+    let selected: Vec<bool> = region.raw_pixels().iter().map(|a| *a == 255).collect();
+    assert_eq!(selected.iter().filter(|s| **s).count(), 14);
+    assert!(!selected[12] && selected[13] && !selected[15]);
+    gradient.flood_fill(0, 1, 40.5 / 255.0, Connectivity::Four, &R { r: 200 }, BlendMode::Alpha);
+    assert_eq!(gradient.get_pixel(4, 2), R { r: 200 });
+    assert_eq!(gradient.get_pixel(5, 2), R { r: 50 });
+}
+
+#[test]
+fn off_canvas_drawing() {
+    let white = RGB { r: 255, g: 255, b: 255 };
+    let filled = |canvas: &BezierCanvas<u32, RGB>| -> Vec<(usize, usize)> {
+        (0..10).flat_map(|y| (0..10).map(move |x| (x, y)))
+            .filter(|(x, y)| canvas.get_pixel(*x, *y) == white)
+            .collect()
+    };
+
+    // geometry entirely off the canvas draws nothing, not even on the edges
+    let mut canvas = BezierCanvas::<u32, RGB>::new(10, 10);
+    canvas.stroke_line(&Vec2::new(-0.5, 0.2), &Vec2::new(-0.1, 0.4), &white, BlendMode::Override);
+    canvas.stroke_line(&Vec2::new(0.3, 1.8), &Vec2::new(0.2, 1.2), &white, BlendMode::Override);
+    canvas.fill_shape(&vec![vec![Vec2::new(-0.5, 0.2), Vec2::new(-0.1, 0.2), Vec2::new(-0.1, 0.8), Vec2::new(-0.5, 0.8)]], &white, BlendMode::Override);
+    assert!(filled(&canvas).is_empty());
+
+    // lines are clipped at the edges, whatever the order of the endpoints
+    let mut canvas = BezierCanvas::<u32, RGB>::new(10, 10);
+    canvas.stroke_line(&Vec2::new(0.55, 0.55), &Vec2::new(-0.5, 0.55), &white, BlendMode::Override);
+    assert_eq!(filled(&canvas), (0..=5).map(|x| (x, 5)).collect::<Vec<_>>());
+
+    // spans past the right edge do not wrap into the next row
+    let mut canvas = BezierCanvas::<u32, RGB>::new(10, 10);
+    canvas.fill_shape(&vec![vec![Vec2::new(0.72, 0.22), Vec2::new(1.5, 0.22), Vec2::new(1.5, 0.78), Vec2::new(0.72, 0.78)]], &white, BlendMode::Override);
+    assert_eq!(filled(&canvas), (2..=7).flat_map(|y| (7..=9).map(move |x| (x, y))).collect::<Vec<_>>());
+
+    // the bounding box includes a maximum at the first point
+    let mut canvas = BezierCanvas::<u32, RGB>::new(10, 10);
+    canvas.fill_shape(&vec![vec![Vec2::new(0.8, 0.8), Vec2::new(0.2, 0.5), Vec2::new(0.5, 0.2)]], &white, BlendMode::Override);
+    assert_eq!(canvas.get_pixel(7, 7), white);
+}
+
+#[test]
+fn tiled_canvas() {
+    let red = RGBA { r: 255, g: 0, b: 0, a: 255 };
+    let blue = RGBA { r: 0, g: 0, b: 255, a: 128 };
+    let curve = [Vec2::new(0.05, 0.9), Vec2::new(0.5, -0.3), Vec2::new(0.95, 0.9)];
+    let star = vec![vec![Vec2::new(0.5, 0.3), Vec2::new(0.62, 0.7), Vec2::new(0.3, 0.45), Vec2::new(0.7, 0.45), Vec2::new(0.38, 0.7)]];
+    let avatar = BezierCanvas::<u32, RGB>::from_png("avatar.png");
+
+    let mut expected = BezierCanvas::<u32, RGBA>::new(100, 70);
+    expected.fill_rect(&Vec2::new(0.1, 0.2), &Vec2::new(0.5, 0.3), &red, BlendMode::Override);
+    expected.fill_circle(&Vec2::new(0.6, 0.5), 0.25, &blue, BlendMode::Alpha);
+    expected.stroke_bezier::<3>(&curve, &red, 100, BlendMode::Override);
+    expected.fill_shape(&star, &blue, BlendMode::Multiply);
+    expected.draw_image_rect::<u32, RGB, LinearFilter>(&avatar, &Vec2::new(0.703, 0.053), &Vec2::new(0.25, 0.31), BlendMode::Alpha, 0.5);
+    let triangle = [
+        shader::VIn { xy: Vec2::new(0.05, 0.1), color: Vec4::new(1.0, 0.0, 1.0, 1.0), uv: Vec2::new(0.5, 0.25) },
+        shader::VIn { xy: Vec2::new(0.9, 0.35), color: Vec4::new(1.0, 1.0, 0.0, 0.5), uv: Vec2::new(0.25, 0.75) },
+        shader::VIn { xy: Vec2::new(0.3, 0.95), color: Vec4::new(0.0, 1.0, 1.0, 1.0), uv: Vec2::new(0.75, 0.75) }
+    ];
+    let uniform = shader::SU { texture: avatar.convert::<u32, RGB>() };
+    expected.shade::<shader::VIn, shader::SU, BVec<f32, 6>, shader::VS, shader::FS>(&triangle, &uniform, BlendMode::Alpha);
+
+    for spill in [None, Some(2)] {
+        // tiles of 16 pixels, partial on the right and bottom
+        let mut tiled = TiledCanvas::<u32, RGBA>::new(100, 70, 16);
+        if let Some(max_resident) = spill {
+            tiled.spill_to_disk(max_resident);
+        }
+        tiled.fill_rect(&Vec2::new(0.1, 0.2), &Vec2::new(0.5, 0.3), &red, BlendMode::Override);
+        if spill.is_none() {
+            // only the tiles under the rect are allocated
+            assert_eq!(tiled.resident_tiles(), 4 * 3);
+        }
+        tiled.fill_circle(&Vec2::new(0.6, 0.5), 0.25, &blue, BlendMode::Alpha);
+        tiled.stroke_bezier::<3>(&curve, &red, 100, BlendMode::Override);
+        tiled.fill_shape(&star, &blue, BlendMode::Multiply);
+        tiled.draw_image_rect::<u32, RGB, LinearFilter>(&avatar, &Vec2::new(0.703, 0.053), &Vec2::new(0.25, 0.31), BlendMode::Alpha, 0.5);
+        tiled.shade::<shader::VIn, shader::SU, BVec<f32, 6>, shader::VS, shader::FS>(&triangle, &uniform, BlendMode::Alpha);
+        if let Some(max_resident) = spill {
+            assert!(tiled.resident_tiles() <= max_resident);
+        }
+        // tiles rasterize in pixels of the whole canvas, so they match it exactly
+        for y in 0..70 {
+            for x in 0..100 {
+                assert_eq!(tiled.get_pixel(x, y), expected.get_pixel(x, y), "({}, {})", x, y);
+            }
+        }
+
+        let path = format!("target/debug/examples/tiled_{}.png", spill.is_some());
+        tiled.export_png(&path);
+        let exported = BezierCanvas::<u32, RGBA>::from_png(&path);
+        for y in 0..70 {
+            for x in 0..100 {
+                assert_eq!(exported.get_pixel(x, y), tiled.get_pixel(x, y), "({}, {})", x, y);
+            }
+        }
+    }
+
+    // plain and tiled canvases share the sample writer, alpha canvases included
+    let mut alpha = TiledCanvas::<u8, A>::new(20, 10, 8);
+    alpha.fill_rect(&Vec2::new(0.2, 0.2), &Vec2::new(0.5, 0.5), &A { a: 200 }, BlendMode::Override);
+    alpha.export_png("target/debug/examples/tiled_alpha.png");
+    let loaded = BezierCanvas::<u8, A>::from_png("target/debug/examples/tiled_alpha.png");
+    assert_eq!((loaded.get_pixel(7, 4), loaded.get_pixel(0, 0)), (A { a: 200 }, A { a: 0 }));
+    loaded.export_png("target/debug/examples/alpha.png");
+    assert_eq!(BezierCanvas::<u8, A>::from_png("target/debug/examples/alpha.png").raw_pixels(), loaded.raw_pixels());
+    let mut gray = BezierCanvas::<u16, RA>::new(3, 2);
+    gray.raw_pixels_mut()[4] = RA { r: 90, a: 30 }.to_value();
+    gray.export_png("target/debug/examples/gray_alpha.png");
+    assert_eq!(BezierCanvas::<u16, RA>::from_png("target/debug/examples/gray_alpha.png").raw_pixels(), gray.raw_pixels());
+}
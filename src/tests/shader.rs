@@ -3,19 +3,20 @@ use crate::convert::PNGCompatible;
 use crate::texture::{LinearFilter, WrapClampToEdge};
 use crate::types::blend::BlendMode;
 use crate::types::colortype::{ColorType, RGB, RGBA};
+use crate::types::gamma::WorkingSpace;
 use crate::linalg::{BVec, Vec2, Vec4};
 use crate::shading::{VertexShader, FragmentShader, VertexOut, FragOut};
 
 pub struct VS {}
 
 pub struct SU {
-    texture: BezierCanvas<u32, RGB>
+    pub texture: BezierCanvas<u32, RGB>
 }
 
 pub struct VIn {
-    xy: Vec2,
-    color: Vec4,
-    uv: Vec2,
+    pub xy: Vec2,
+    pub color: Vec4,
+    pub uv: Vec2,
 }
 impl VertexShader for VS {
     type Attribute = VIn;
@@ -24,7 +25,7 @@ impl VertexShader for VS {
     type Out = BVec<f32, 6>;
 
     fn shade(attr: &Self::Attribute, _uniform: &Self::Uniform) -> VertexOut<Self::Out> {
-        let ans = VertexOut::<BVec<f32, 6>> {
+        VertexOut::<BVec<f32, 6>> {
             coord: attr.xy,
             varying: BVec {
                 v: [
@@ -36,8 +37,12 @@ impl VertexShader for VS {
                     attr.color.w(),
                 ]
             },
-        };
-        ans
+        }
+    }
+
+    fn map_colors<F: Fn(Vec4) -> Vec4>(varying: &mut Self::Out, f: F) {
+        let color = f(Vec4::new(varying.v[2], varying.v[3], varying.v[4], varying.v[5]));
+        varying.v[2..].copy_from_slice(&color.v);
     }
 }
 
@@ -107,4 +112,51 @@ fn shade() {
         canvas.stroke_bezier::<4>(&poses[i - 1..i + 3], &RGBA {r: 255, g: 255, b: 255, a: 255}, 50, BlendMode::Alpha);
     }
     canvas.export_png("target/debug/examples/triangle.png");
-}
\ No newline at end of file
+}
+
+// vertex colors only, to check how gradients are interpolated
+pub struct GradientVS {}
+pub struct GradientFS {}
+
+impl VertexShader for GradientVS {
+    type Attribute = (Vec2, Vec4);
+    type Uniform = ();
+    type Out = Vec4;
+
+    fn shade(attr: &Self::Attribute, _uniform: &Self::Uniform) -> VertexOut<Self::Out> {
+        VertexOut::new(attr.0, attr.1)
+    }
+
+    fn map_colors<F: Fn(Vec4) -> Vec4>(varying: &mut Self::Out, f: F) {
+        *varying = f(*varying);
+    }
+}
+
+impl FragmentShader for GradientFS {
+    type In = Vec4;
+    type Uniform = ();
+    type InternalType = u32;
+    type ExternalType = RGB;
+
+    fn shade(attribute: &Self::In, _uniform: &Self::Uniform) -> FragOut<Self::InternalType, Self::ExternalType> {
+        FragOut::new(RGB::from_vec4(*attribute), 0.0)
+    }
+}
+
+#[test]
+fn linear_gradient() {
+    let black = Vec4::new(0.0, 0.0, 0.0, 1.0);
+    let white = Vec4::new(1.0, 1.0, 1.0, 1.0);
+    let quad = [
+        (Vec2::new(0.0, 0.0), black), (Vec2::new(1.0, 0.0), white), (Vec2::new(0.0, 1.0), black),
+        (Vec2::new(1.0, 0.0), white), (Vec2::new(1.0, 1.0), white), (Vec2::new(0.0, 1.0), black)
+    ];
+    let mut canvas = BezierCanvas::<u32, RGB>::new(3, 1);
+    canvas.shade::<(Vec2, Vec4), (), Vec4, GradientVS, GradientFS>(&quad, &(), BlendMode::Override);
+    assert_eq!(canvas.get_pixel(1, 0), RGB { r: 128, g: 128, b: 128 });
+    // halfway between black and white is half the light, much brighter than 128 once sRGB encoded
+    canvas.working_space = WorkingSpace::Linear;
+    canvas.shade::<(Vec2, Vec4), (), Vec4, GradientVS, GradientFS>(&quad, &(), BlendMode::Override);
+    assert_eq!(canvas.get_pixel(1, 0), RGB { r: 188, g: 188, b: 188 });
+    assert_eq!(canvas.get_pixel(0, 0).r, (255.0 * crate::types::gamma::linear_to_srgb(1.0 / 6.0)).round() as u8);
+}
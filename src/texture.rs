@@ -15,4 +15,12 @@ pub struct WrapClampToEdge {}
 
 pub trait Wrapping {
     fn wrap(x: f32) -> f32;
+    // texel index, for filters reading whole texels; texels sit at uv = i / (max - 1), as in sampling
+    fn wrap_index(i: isize, max: usize) -> usize {
+        if max <= 1 {
+            return 0;
+        }
+        let last = (max - 1) as f32;
+        ((Self::wrap(i as f32 / last) * last).round() as usize).min(max - 1)
+    }
 }
\ No newline at end of file
@@ -1,5 +1,8 @@
+use num::Zero;
+
 use crate::types::colortype::{InternalColorType, ColorType};
-use crate::linalg::Vec4;
+use crate::types::gamma::WorkingSpace;
+use crate::linalg::{Vec3, Vec4};
 
 // Since blending can be determined through run time, use enum.
 #[derive(Copy, Clone)]
@@ -7,33 +10,185 @@ pub enum BlendMode {
     Override,
     Alpha,
     Multiply,
-    Screen
+    Screen,
+    Overlay,
+    Darken,
+    Lighten,
+    ColorDodge,
+    ColorBurn,
+    HardLight,
+    SoftLight,
+    Difference,
+    Exclusion,
+    Hue,
+    Saturation,
+    Color,
+    Luminosity
 }
 impl BlendMode {
     pub fn blend<InternalType: InternalColorType, ExternalType: ColorType<InternalType>>(&self, bg: InternalType, fg: &ExternalType) -> InternalType {
+        self.blend_in(bg, fg, WorkingSpace::Gamma)
+    }
+
+    pub fn blend_in<InternalType: InternalColorType, ExternalType: ColorType<InternalType>>(&self, bg: InternalType, fg: &ExternalType, space: WorkingSpace) -> InternalType {
         match self {
             BlendMode::Override => fg.to_value(),
             BlendMode::Alpha => {
-                let fg_vec = fg.to_vec4();
-                let bg_vec = ExternalType::from_value(bg).to_vec4();
-                let alpha = fg_vec.w();
-                let mut ans = bg_vec * (1f32 - alpha) + fg_vec * alpha;
-                ans.v[3] = 1.0 - (1.0 - bg_vec.w()) * (1.0 - fg_vec.w());
-                ExternalType::from_vec4(ans).to_value()
+                // source-over is linear in premultiplied alpha
+                let fg_vec = space.to_premul_vec4(fg);
+                let bg_vec = space.to_premul_vec4(&ExternalType::from_value(bg));
+                let ans = fg_vec + bg_vec * (1f32 - fg_vec.w());
+                space.from_premul_vec4::<InternalType, ExternalType>(ans).to_value()
             },
-            BlendMode::Multiply => {
-                let fg_vec = fg.to_vec4();
-                let bg_vec = ExternalType::from_value(bg).to_vec4();
-                let ans = fg_vec.star(&bg_vec);
-                ExternalType::from_vec4(ans).to_value()
+            _ => {
+                let fg_vec = space.to_vec4(fg);
+                let bg_vec = space.to_vec4(&ExternalType::from_value(bg));
+                space.from_vec4::<InternalType, ExternalType>(self.composite(bg_vec, fg_vec)).to_value()
             },
-            BlendMode::Screen => {
-                let full = Vec4::new(1.0, 1.0, 1.0, 1.0);
-                let fg_vec = full - fg.to_vec4();
-                let bg_vec = full - ExternalType::from_value(bg).to_vec4();
-                let ans = full - fg_vec.star(&bg_vec);
-                ExternalType::from_vec4(ans).to_value()
+        }
+    }
+
+    // blend straight-alpha colors, already decoded to the working space
+    pub fn blend_vec4(&self, bg: Vec4, fg: Vec4) -> Vec4 {
+        match self {
+            BlendMode::Override => fg,
+            _ => self.composite(bg, fg),
+        }
+    }
+
+    /*
+        W3C compositing, source-over with a blend function B:
+            Cs' = (1 - ab) * Cs + ab * B(Cb, Cs)
+            ao  = as + ab * (1 - as)
+            Co  = (as * Cs' + ab * (1 - as) * Cb) / ao
+        all colors here are straight (non-premultiplied) alpha.
+        B works on a channel basis for separable modes, and on the whole RGB triple for non-separable modes.
+     */
+    fn composite(&self, bg: Vec4, fg: Vec4) -> Vec4 {
+        let alpha_b = bg.w();
+        let alpha_s = fg.w();
+        let alpha_o = alpha_s + alpha_b * (1.0 - alpha_s);
+        let mut ans = Vec4::zero();
+        if alpha_o <= 0.0 {
+            return ans;
+        }
+        let mixed = self.mix(bg.xyz(), fg.xyz());
+        for i in 0..3 {
+            let mixed = (1.0 - alpha_b) * fg.v[i] + alpha_b * mixed.v[i];
+            ans.v[i] = (alpha_s * mixed + alpha_b * (1.0 - alpha_s) * bg.v[i]) / alpha_o;
+        }
+        ans.v[3] = alpha_o;
+        ans
+    }
+
+    // blend function B(Cb, Cs) of a RGB triple
+    fn mix(&self, cb: Vec3, cs: Vec3) -> Vec3 {
+        match self {
+            BlendMode::Hue => set_lum(set_sat(cs, sat(cb)), lum(cb)),
+            BlendMode::Saturation => set_lum(set_sat(cb, sat(cs)), lum(cb)),
+            BlendMode::Color => set_lum(cs, lum(cb)),
+            BlendMode::Luminosity => set_lum(cb, lum(cs)),
+            _ => {
+                let mut ans = Vec3::zero();
+                for i in 0..3 {
+                    ans.v[i] = self.separable(cb.v[i], cs.v[i]);
+                }
+                ans
+            }
+        }
+    }
+
+    // blend function B(Cb, Cs) of a single channel
+    fn separable(&self, cb: f32, cs: f32) -> f32 {
+        match self {
+            BlendMode::Override | BlendMode::Alpha
+            | BlendMode::Hue | BlendMode::Saturation | BlendMode::Color | BlendMode::Luminosity => cs,
+            BlendMode::Multiply => cb * cs,
+            BlendMode::Screen => cb + cs - cb * cs,
+            BlendMode::Overlay => BlendMode::HardLight.separable(cs, cb),
+            BlendMode::Darken => cb.min(cs),
+            BlendMode::Lighten => cb.max(cs),
+            BlendMode::ColorDodge => {
+                if cb <= 0.0 {
+                    0.0
+                } else if cs >= 1.0 {
+                    1.0
+                } else {
+                    (cb / (1.0 - cs)).min(1.0)
+                }
             },
+            BlendMode::ColorBurn => {
+                if cb >= 1.0 {
+                    1.0
+                } else if cs <= 0.0 {
+                    0.0
+                } else {
+                    1.0 - ((1.0 - cb) / cs).min(1.0)
+                }
+            },
+            BlendMode::HardLight => {
+                if cs <= 0.5 {
+                    BlendMode::Multiply.separable(cb, 2.0 * cs)
+                } else {
+                    BlendMode::Screen.separable(cb, 2.0 * cs - 1.0)
+                }
+            },
+            BlendMode::SoftLight => {
+                if cs <= 0.5 {
+                    cb - (1.0 - 2.0 * cs) * cb * (1.0 - cb)
+                } else {
+                    let d = if cb <= 0.25 {
+                        ((16.0 * cb - 12.0) * cb + 4.0) * cb
+                    } else {
+                        cb.sqrt()
+                    };
+                    cb + (2.0 * cs - 1.0) * (d - cb)
+                }
+            },
+            BlendMode::Difference => (cb - cs).abs(),
+            BlendMode::Exclusion => cb + cs - 2.0 * cb * cs,
+        }
+    }
+}
+
+// helpers of non-separable blend modes, see W3C compositing spec
+fn lum(c: Vec3) -> f32 {
+    0.3 * c.x() + 0.59 * c.y() + 0.11 * c.z()
+}
+
+fn clip_color(c: Vec3) -> Vec3 {
+    let l = lum(c);
+    let n = c.x().min(c.y()).min(c.z());
+    let x = c.x().max(c.y()).max(c.z());
+    let mut ans = c;
+    for i in 0..3 {
+        if n < 0.0 {
+            ans.v[i] = l + (ans.v[i] - l) * l / (l - n);
+        }
+        if x > 1.0 {
+            ans.v[i] = l + (ans.v[i] - l) * (1.0 - l) / (x - l);
         }
     }
-}
\ No newline at end of file
+    ans
+}
+
+fn set_lum(c: Vec3, l: f32) -> Vec3 {
+    let d = l - lum(c);
+    clip_color(Vec3::new(c.x() + d, c.y() + d, c.z() + d))
+}
+
+fn sat(c: Vec3) -> f32 {
+    c.x().max(c.y()).max(c.z()) - c.x().min(c.y()).min(c.z())
+}
+
+fn set_sat(c: Vec3, s: f32) -> Vec3 {
+    let mut order = [0usize, 1, 2];
+    order.sort_by(|a, b| c.v[*a].partial_cmp(&c.v[*b]).unwrap());
+    let [min, mid, max] = order;
+    let mut ans = Vec3::zero();
+    if c.v[max] > c.v[min] {
+        ans.v[mid] = (c.v[mid] - c.v[min]) * s / (c.v[max] - c.v[min]);
+        ans.v[max] = s;
+    }
+    ans
+}
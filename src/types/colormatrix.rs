@@ -0,0 +1,136 @@
+use num::{One, Zero};
+
+use crate::linalg::{Matrix4, Vec4};
+use crate::types::curve::Curve;
+
+/*
+    Affine map of straight-alpha RGBA colors, as SVG `feColorMatrix`:
+        out = matrix * color + offset
+    The presets follow the SVG and CSS filter effects definitions.
+ */
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ColorMatrix {
+    pub matrix: Matrix4,
+    pub offset: Vec4
+}
+
+// luminance weights used by the SVG and CSS presets
+const LUMA: [f32; 3] = [0.213, 0.715, 0.072];
+
+impl ColorMatrix {
+    pub fn new(matrix: Matrix4, offset: Vec4) -> Self {
+        ColorMatrix { matrix, offset }
+    }
+
+    pub fn identity() -> Self {
+        ColorMatrix::new(Matrix4::one(), Vec4::zero())
+    }
+
+    // rgb rows given as a 3x3 matrix, alpha unchanged
+    fn rgb(rows: [[f32; 3]; 3], offset: f32) -> Self {
+        let mut matrix = Matrix4::one();
+        for (i, row) in rows.iter().enumerate() {
+            matrix.v[i][..3].copy_from_slice(row);
+        }
+        ColorMatrix::new(matrix, Vec4::new(offset, offset, offset, 0.0))
+    }
+
+    pub fn apply(&self, color: Vec4) -> Vec4 {
+        self.matrix * color + self.offset
+    }
+
+    // `self` followed by `next`
+    pub fn then(&self, next: &ColorMatrix) -> Self {
+        ColorMatrix::new(next.matrix * self.matrix, next.matrix * self.offset + next.offset)
+    }
+
+    // 1 keeps the colors, 0 is fully desaturated (grayscale), as in SVG feColorMatrix
+    pub fn saturate(s: f32) -> Self {
+        let mut rows = [[0.0; 3]; 3];
+        for (i, row) in rows.iter_mut().enumerate() {
+            for (j, c) in row.iter_mut().enumerate() {
+                *c = LUMA[j] * (1.0 - s) + if i == j { s } else { 0.0 };
+            }
+        }
+        ColorMatrix::rgb(rows, 0.0)
+    }
+
+    pub fn grayscale(amount: f32) -> Self {
+        ColorMatrix::saturate(1.0 - amount)
+    }
+
+    pub fn sepia(amount: f32) -> Self {
+        let k = 1.0 - amount;
+        ColorMatrix::rgb([
+            [0.393 + 0.607 * k, 0.769 - 0.769 * k, 0.189 - 0.189 * k],
+            [0.349 - 0.349 * k, 0.686 + 0.314 * k, 0.168 - 0.168 * k],
+            [0.272 - 0.272 * k, 0.534 - 0.534 * k, 0.131 + 0.869 * k]
+        ], 0.0)
+    }
+
+    pub fn hue_rotate(degrees: f32) -> Self {
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        let cos_part = [
+            [ 0.787, -0.715, -0.072],
+            [-0.213,  0.285, -0.072],
+            [-0.213, -0.715,  0.928]
+        ];
+        let sin_part = [
+            [-0.213, -0.715,  0.928],
+            [ 0.143,  0.140, -0.283],
+            [-0.787,  0.715,  0.072]
+        ];
+        let mut rows = [[0.0; 3]; 3];
+        for (i, row) in rows.iter_mut().enumerate() {
+            for (j, c) in row.iter_mut().enumerate() {
+                *c = LUMA[j] + cos * cos_part[i][j] + sin * sin_part[i][j];
+            }
+        }
+        ColorMatrix::rgb(rows, 0.0)
+    }
+
+    pub fn invert() -> Self {
+        ColorMatrix::rgb([[-1.0, 0.0, 0.0], [0.0, -1.0, 0.0], [0.0, 0.0, -1.0]], 1.0)
+    }
+
+    // CSS semantics: brightness scales the colors, and then contrast scales them around the middle gray, 1 is unchanged
+    pub fn brightness_contrast(brightness: f32, contrast: f32) -> Self {
+        let k = brightness * contrast;
+        ColorMatrix::rgb([[k, 0.0, 0.0], [0.0, k, 0.0], [0.0, 0.0, k]], 0.5 - 0.5 * contrast)
+    }
+}
+
+// per-channel transfer function, as SVG `feComponentTransfer`
+#[derive(Debug, Clone, PartialEq)]
+pub enum Transfer {
+    Identity,
+    // slope * c + intercept
+    Linear { slope: f32, intercept: f32 },
+    // amplitude * c ^ exponent + offset
+    Gamma { amplitude: f32, exponent: f32, offset: f32 },
+    // piecewise linear through evenly spaced values over [0, 1]
+    Table(Vec<f32>),
+    Curve(Curve)
+}
+
+impl Transfer {
+    pub fn apply(&self, c: f32) -> f32 {
+        match self {
+            Transfer::Identity => c,
+            Transfer::Linear { slope, intercept } => slope * c + intercept,
+            Transfer::Gamma { amplitude, exponent, offset } => amplitude * c.max(0.0).powf(*exponent) + offset,
+            Transfer::Table(values) => {
+                match values.len() {
+                    0 => c,
+                    1 => values[0],
+                    n => {
+                        let x = c.clamp(0.0, 1.0) * (n - 1) as f32;
+                        let k = (x.floor() as usize).min(n - 2);
+                        values[k] + (values[k + 1] - values[k]) * (x - k as f32)
+                    }
+                }
+            },
+            Transfer::Curve(curve) => curve.eval(c),
+        }
+    }
+}
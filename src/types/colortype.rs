@@ -5,12 +5,42 @@ pub trait InternalColorType: Sync + Send + Zero + Clone + Copy {}
 impl InternalColorType for u8 {}
 impl InternalColorType for u16 {}
 impl InternalColorType for u32 {}
+impl InternalColorType for u64 {}
+impl InternalColorType for Vec4 {}
 
-pub trait ColorType<T: InternalColorType>: Sync + Clone + Copy + Eq + PartialEq{
+pub trait ColorType<T: InternalColorType>: Sync + Clone + Copy + PartialEq{
     fn from_value(raw: T) -> Self;
     fn to_value(&self) -> T;
     fn from_vec4(raw: Vec4) -> Self;
     fn to_vec4(&self) -> Vec4;
+    // premultiplied-alpha counterparts of `from_vec4` and `to_vec4`, overridden by types storing premultiplied alpha.
+    fn from_premul_vec4(raw: Vec4) -> Self {
+        Self::from_vec4(unpremultiply(raw))
+    }
+    fn to_premul_vec4(&self) -> Vec4 {
+        premultiply(self.to_vec4())
+    }
+    // quantization step of each channel, as used by dithering
+    fn step() -> Vec4 {
+        Vec4::new(1.0 / 255.0, 1.0 / 255.0, 1.0 / 255.0, 1.0 / 255.0)
+    }
+}
+
+/*
+    `ColorType` only requires `PartialEq`, as `RGBAF32` stores floats; generic code relying on `Eq` of color types needs another bound.
+    All color types stored as integers are `Eq`, and are `IntegerColorType`.
+ */
+pub trait IntegerColorType<T: InternalColorType>: ColorType<T> + Eq {}
+impl <T: InternalColorType, C: ColorType<T> + Eq> IntegerColorType<T> for C {}
+
+pub fn premultiply(raw: Vec4) -> Vec4 {
+    Vec4::new(raw.x() * raw.w(), raw.y() * raw.w(), raw.z() * raw.w(), raw.w())
+}
+pub fn unpremultiply(raw: Vec4) -> Vec4 {
+    if raw.w() <= 0.0 {
+        return Vec4::new(0.0, 0.0, 0.0, 0.0);
+    }
+    Vec4::new(raw.x() / raw.w(), raw.y() / raw.w(), raw.z() / raw.w(), raw.w())
 }
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
 pub struct A {
@@ -38,6 +68,79 @@ pub struct RGBA {
     pub b: u8,
     pub a: u8,
 }
+/*
+    Packed formats, laid out as consumed by display and video code.
+    BGRA and ARGB are 8 bits per channel in the named byte order in memory, i.e. the value is built from its bytes in native endianness, so the layout holds on every host.
+    RGB565 and RGBA4444 pack channels from the most significant bit of a 16-bit value.
+ */
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct BGRA {
+    pub b: u8,
+    pub g: u8,
+    pub r: u8,
+    pub a: u8,
+}
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct ARGB {
+    pub a: u8,
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+// r and b are 5 bits, g is 6 bits
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct RGB565 {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+// 4 bits per channel
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct RGBA4444 {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+// 16 bits per channel
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct R16 {
+    pub r: u16
+}
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct RA16 {
+    pub r: u16,
+    pub a: u16
+}
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct RGB16 {
+    pub r: u16,
+    pub g: u16,
+    pub b: u16,
+}
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct RGBA16 {
+    pub r: u16,
+    pub g: u16,
+    pub b: u16,
+    pub a: u16,
+}
+// 32-bit float per channel, not clamped, so values above 1.0 can hold high dynamic range
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RGBAF32 {
+    pub r: f32,
+    pub g: f32,
+    pub b: f32,
+    pub a: f32,
+}
+// RGBA with color channels premultiplied by alpha
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct PremulRGBA {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
 impl ColorType<u8> for A {
     fn from_value(raw: u8) -> Self {
         Self {a: raw}
@@ -114,3 +217,185 @@ impl ColorType<u32> for RGBA {
         Self {r: (raw.x() * 255f32).round() as u8, g: (raw.y() * 255f32).round() as u8, b: (raw.z() * 255f32).round() as u8, a: (raw.w() * 255f32).round() as u8}
     }
 }
+
+impl ColorType<u32> for PremulRGBA {
+    fn from_value(raw: u32) -> Self {
+        Self { a: (raw & 0xff) as u8, r: ((raw >> 8) & 0xff) as u8, g: ((raw >> 16) & 0xff) as u8, b: ((raw >> 24) & 0xff) as u8 }
+    }
+    fn to_value(&self) -> u32 {
+        (self.a as u32) | ((self.r as u32) << 8) | ((self.g as u32) << 16) | ((self.b as u32) << 24)
+    }
+    fn to_vec4(&self) -> Vec4 {
+        unpremultiply(self.to_premul_vec4())
+    }
+    fn from_vec4(raw: Vec4) -> Self {
+        Self::from_premul_vec4(premultiply(raw))
+    }
+    fn to_premul_vec4(&self) -> Vec4 {
+        Vec4::new(self.r as f32 / 255f32, self.g as f32 / 255f32, self.b as f32 / 255f32, self.a as f32 / 255f32)
+    }
+    fn from_premul_vec4(raw: Vec4) -> Self {
+        Self {r: (raw.x() * 255f32).round() as u8, g: (raw.y() * 255f32).round() as u8, b: (raw.z() * 255f32).round() as u8, a: (raw.w() * 255f32).round() as u8}
+    }
+}
+
+impl ColorType<u16> for R16 {
+    fn from_value(raw: u16) -> Self {
+        Self {r: raw}
+    }
+    fn to_value(&self) -> u16 {
+        self.r
+    }
+    fn to_vec4(&self) -> Vec4 {
+        Vec4::new(self.r as f32 / 65535f32, self.r as f32 / 65535f32, self.r as f32 / 65535f32, 1.0)
+    }
+    fn from_vec4(raw: Vec4) -> Self {
+        Self {r: ((raw.x() + raw.y() + raw.z()) * 21845f32).round() as u16}
+    }
+    fn step() -> Vec4 {
+        Vec4::new(1.0 / 65535.0, 1.0 / 65535.0, 1.0 / 65535.0, 0.0)
+    }
+}
+
+impl ColorType<u32> for RA16 {
+    fn from_value(raw: u32) -> Self {
+        Self { r: ((raw >> 16) & 0xffff) as u16, a: (raw & 0xffff) as u16 }
+    }
+    fn to_value(&self) -> u32 {
+        (self.a as u32) | ((self.r as u32) << 16)
+    }
+    fn to_vec4(&self) -> Vec4 {
+        Vec4::new(self.r as f32 / 65535f32, self.r as f32 / 65535f32, self.r as f32 / 65535f32, self.a as f32 / 65535f32)
+    }
+    fn from_vec4(raw: Vec4) -> Self {
+        Self {r: ((raw.x() + raw.y() + raw.z()) * 21845f32).round() as u16, a: (raw.w() * 65535f32).round() as u16}
+    }
+    fn step() -> Vec4 {
+        Vec4::new(1.0 / 65535.0, 1.0 / 65535.0, 1.0 / 65535.0, 1.0 / 65535.0)
+    }
+}
+
+impl ColorType<u64> for RGB16 {
+    fn from_value(raw: u64) -> Self {
+        Self { r: ((raw >> 16) & 0xffff) as u16, g: ((raw >> 32) & 0xffff) as u16, b: ((raw >> 48) & 0xffff) as u16 }
+    }
+    fn to_value(&self) -> u64 {
+        ((self.r as u64) << 16) | ((self.g as u64) << 32) | ((self.b as u64) << 48)
+    }
+    fn to_vec4(&self) -> Vec4 {
+        Vec4::new(self.r as f32 / 65535f32, self.g as f32 / 65535f32, self.b as f32 / 65535f32, 1.0)
+    }
+    fn from_vec4(raw: Vec4) -> Self {
+        Self {r: (raw.x() * 65535f32).round() as u16, g: (raw.y() * 65535f32).round() as u16, b: (raw.z() * 65535f32).round() as u16}
+    }
+    fn step() -> Vec4 {
+        Vec4::new(1.0 / 65535.0, 1.0 / 65535.0, 1.0 / 65535.0, 0.0)
+    }
+}
+
+impl ColorType<u64> for RGBA16 {
+    fn from_value(raw: u64) -> Self {
+        Self { a: (raw & 0xffff) as u16, r: ((raw >> 16) & 0xffff) as u16, g: ((raw >> 32) & 0xffff) as u16, b: ((raw >> 48) & 0xffff) as u16 }
+    }
+    fn to_value(&self) -> u64 {
+        (self.a as u64) | ((self.r as u64) << 16) | ((self.g as u64) << 32) | ((self.b as u64) << 48)
+    }
+    fn to_vec4(&self) -> Vec4 {
+        Vec4::new(self.r as f32 / 65535f32, self.g as f32 / 65535f32, self.b as f32 / 65535f32, self.a as f32 / 65535f32)
+    }
+    fn from_vec4(raw: Vec4) -> Self {
+        Self {r: (raw.x() * 65535f32).round() as u16, g: (raw.y() * 65535f32).round() as u16, b: (raw.z() * 65535f32).round() as u16, a: (raw.w() * 65535f32).round() as u16}
+    }
+    fn step() -> Vec4 {
+        Vec4::new(1.0 / 65535.0, 1.0 / 65535.0, 1.0 / 65535.0, 1.0 / 65535.0)
+    }
+}
+
+impl ColorType<Vec4> for RGBAF32 {
+    fn from_value(raw: Vec4) -> Self {
+        Self { r: raw.x(), g: raw.y(), b: raw.z(), a: raw.w() }
+    }
+    fn to_value(&self) -> Vec4 {
+        Vec4::new(self.r, self.g, self.b, self.a)
+    }
+    fn to_vec4(&self) -> Vec4 {
+        self.to_value()
+    }
+    fn from_vec4(raw: Vec4) -> Self {
+        Self::from_value(raw)
+    }
+    fn step() -> Vec4 {
+        Vec4::new(0.0, 0.0, 0.0, 0.0)
+    }
+}
+
+impl ColorType<u32> for BGRA {
+    fn from_value(raw: u32) -> Self {
+        let [b, g, r, a] = raw.to_ne_bytes();
+        Self { b, g, r, a }
+    }
+    fn to_value(&self) -> u32 {
+        u32::from_ne_bytes([self.b, self.g, self.r, self.a])
+    }
+    fn to_vec4(&self) -> Vec4 {
+        Vec4::new(self.r as f32 / 255f32, self.g as f32 / 255f32, self.b as f32 / 255f32, self.a as f32 / 255f32)
+    }
+    fn from_vec4(raw: Vec4) -> Self {
+        Self {r: (raw.x() * 255f32).round() as u8, g: (raw.y() * 255f32).round() as u8, b: (raw.z() * 255f32).round() as u8, a: (raw.w() * 255f32).round() as u8}
+    }
+}
+
+impl ColorType<u32> for ARGB {
+    fn from_value(raw: u32) -> Self {
+        let [a, r, g, b] = raw.to_ne_bytes();
+        Self { a, r, g, b }
+    }
+    fn to_value(&self) -> u32 {
+        u32::from_ne_bytes([self.a, self.r, self.g, self.b])
+    }
+    fn to_vec4(&self) -> Vec4 {
+        Vec4::new(self.r as f32 / 255f32, self.g as f32 / 255f32, self.b as f32 / 255f32, self.a as f32 / 255f32)
+    }
+    fn from_vec4(raw: Vec4) -> Self {
+        Self {r: (raw.x() * 255f32).round() as u8, g: (raw.y() * 255f32).round() as u8, b: (raw.z() * 255f32).round() as u8, a: (raw.w() * 255f32).round() as u8}
+    }
+}
+
+impl ColorType<u16> for RGB565 {
+    fn from_value(raw: u16) -> Self {
+        Self { r: ((raw >> 11) & 0x1f) as u8, g: ((raw >> 5) & 0x3f) as u8, b: (raw & 0x1f) as u8 }
+    }
+    fn to_value(&self) -> u16 {
+        ((self.r as u16) << 11) | ((self.g as u16) << 5) | (self.b as u16)
+    }
+    fn to_vec4(&self) -> Vec4 {
+        Vec4::new(self.r as f32 / 31f32, self.g as f32 / 63f32, self.b as f32 / 31f32, 1.0)
+    }
+    fn from_vec4(raw: Vec4) -> Self {
+        // clamped, as channels past their maximum would spill into the next field
+        let c = |x: f32, max: f32| (x.clamp(0.0, 1.0) * max).round() as u8;
+        Self {r: c(raw.x(), 31f32), g: c(raw.y(), 63f32), b: c(raw.z(), 31f32)}
+    }
+    fn step() -> Vec4 {
+        Vec4::new(1.0 / 31.0, 1.0 / 63.0, 1.0 / 31.0, 0.0)
+    }
+}
+
+impl ColorType<u16> for RGBA4444 {
+    fn from_value(raw: u16) -> Self {
+        Self { r: ((raw >> 12) & 0xf) as u8, g: ((raw >> 8) & 0xf) as u8, b: ((raw >> 4) & 0xf) as u8, a: (raw & 0xf) as u8 }
+    }
+    fn to_value(&self) -> u16 {
+        ((self.r as u16) << 12) | ((self.g as u16) << 8) | ((self.b as u16) << 4) | (self.a as u16)
+    }
+    fn to_vec4(&self) -> Vec4 {
+        Vec4::new(self.r as f32 / 15f32, self.g as f32 / 15f32, self.b as f32 / 15f32, self.a as f32 / 15f32)
+    }
+    fn from_vec4(raw: Vec4) -> Self {
+        let c = |x: f32| (x.clamp(0.0, 1.0) * 15f32).round() as u8;
+        Self {r: c(raw.x()), g: c(raw.y()), b: c(raw.z()), a: c(raw.w())}
+    }
+    fn step() -> Vec4 {
+        Vec4::new(1.0 / 15.0, 1.0 / 15.0, 1.0 / 15.0, 1.0 / 15.0)
+    }
+}
@@ -0,0 +1,10 @@
+// Differences between two canvases, on channels of straight colors in [0, 1]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Comparison {
+    pub max_error: f32,
+    pub mean_error: f32,
+    // peak signal-to-noise ratio in decibels, infinite for identical canvases
+    pub psnr: f32,
+    // mean structural similarity of the luma, 1 for identical canvases
+    pub ssim: f32
+}
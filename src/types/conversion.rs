@@ -0,0 +1,30 @@
+use crate::linalg::Vec4;
+
+// Luma weights of R'G'B', applied to encoded colors
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Luma {
+    Rec601,
+    Rec709
+}
+
+impl Luma {
+    pub fn luma(&self, color: Vec4) -> f32 {
+        let [r, g, b] = match self {
+            Luma::Rec601 => [0.299, 0.587, 0.114],
+            Luma::Rec709 => [0.2126, 0.7152, 0.0722],
+        };
+        r * color.x() + g * color.y() + b * color.z()
+    }
+}
+
+/*
+    Options of `BezierCanvas::convert_with`, the default converts through `to_vec4` and `from_vec4` only,
+    e.g. dropping alpha as is, and graying with the plain average of the channels for `R`.
+ */
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct Conversion {
+    // opaque color as returned by `to_vec4`, composited under the canvas in its working space so alpha can be dropped
+    pub background: Option<Vec4>,
+    // gray out colors with these weights
+    pub grayscale: Option<Luma>
+}
@@ -0,0 +1,79 @@
+use crate::linalg::Vec2;
+
+/*
+    Monotone cubic spline through control points, with the tangents limited as by Fritsch and Carlson (1980)
+    so the curve does not overshoot between points, as a "curves" adjustment needs.
+    Outside the control points the curve is constant.
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct Curve {
+    points: Vec<Vec2>,
+    tangents: Vec<f32>
+}
+
+impl Curve {
+    // control points in any order; without points the curve is the identity
+    pub fn new(points: &[Vec2]) -> Self {
+        let mut points = points.to_vec();
+        points.sort_by(|a, b| a.x().partial_cmp(&b.x()).unwrap());
+        points.dedup_by(|a, b| a.x() == b.x());
+        let n = points.len();
+        if n < 2 {
+            return Curve { tangents: vec![0.0; n], points };
+        }
+        let secants: Vec<f32> = points.windows(2)
+            .map(|p| (p[1].y() - p[0].y()) / (p[1].x() - p[0].x()))
+            .collect();
+        let mut tangents = vec![0.0; n];
+        tangents[0] = secants[0];
+        tangents[n - 1] = secants[n - 2];
+        for i in 1..n - 1 {
+            if secants[i - 1] * secants[i] > 0.0 {
+                tangents[i] = (secants[i - 1] + secants[i]) / 2.0;
+            }
+        }
+        for (i, secant) in secants.iter().enumerate() {
+            if *secant == 0.0 {
+                tangents[i] = 0.0;
+                tangents[i + 1] = 0.0;
+                continue;
+            }
+            let a = tangents[i] / secant;
+            let b = tangents[i + 1] / secant;
+            let s = a * a + b * b;
+            if s > 9.0 {
+                let t = 3.0 / s.sqrt();
+                tangents[i] = t * a * secant;
+                tangents[i + 1] = t * b * secant;
+            }
+        }
+        Curve { points, tangents }
+    }
+
+    pub fn eval(&self, x: f32) -> f32 {
+        let points = &self.points;
+        match points.len() {
+            0 => return x,
+            1 => return points[0].y(),
+            _ => {},
+        }
+        let last = points.len() - 1;
+        if x <= points[0].x() {
+            return points[0].y();
+        }
+        if x >= points[last].x() {
+            return points[last].y();
+        }
+        let i = points.partition_point(|p| p.x() <= x) - 1;
+        let (p0, p1) = (points[i], points[i + 1]);
+        let h = p1.x() - p0.x();
+        let t = (x - p0.x()) / h;
+        // cubic Hermite basis
+        let t2 = t * t;
+        let t3 = t2 * t;
+        (2.0 * t3 - 3.0 * t2 + 1.0) * p0.y()
+            + (t3 - 2.0 * t2 + t) * h * self.tangents[i]
+            + (-2.0 * t3 + 3.0 * t2) * p1.y()
+            + (t3 - t2) * h * self.tangents[i + 1]
+    }
+}
@@ -0,0 +1,128 @@
+use std::sync::OnceLock;
+
+use crate::types::colortype::{InternalColorType, ColorType};
+use crate::linalg::Vec4;
+
+/*
+    Dithering applied when a `Vec4` color is quantized to a color type.
+
+    Bayer and BlueNoise are ordered: a threshold depending only on the pixel position offsets the color before rounding,
+    so they can be applied to each fragment independently.
+    FloydSteinberg diffuses the rounding error to the neighboring pixels, so it only applies to whole-canvas conversions,
+    and quantizes without dithering elsewhere.
+ */
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum Dither {
+    #[default]
+    None,
+    Bayer,
+    BlueNoise,
+    FloydSteinberg
+}
+
+const BAYER: [[u8; 8]; 8] = [
+    [ 0, 32,  8, 40,  2, 34, 10, 42],
+    [48, 16, 56, 24, 50, 18, 58, 26],
+    [12, 44,  4, 36, 14, 46,  6, 38],
+    [60, 28, 52, 20, 62, 30, 54, 22],
+    [ 3, 35, 11, 43,  1, 33,  9, 41],
+    [51, 19, 59, 27, 49, 17, 57, 25],
+    [15, 47,  7, 39, 13, 45,  5, 37],
+    [63, 31, 55, 23, 61, 29, 53, 21]
+];
+
+const BLUE_NOISE_SIZE: usize = 32;
+
+impl Dither {
+    // threshold in [0, 1) of the pixel at (x, y), 0.5 means no offset
+    pub fn threshold(&self, x: usize, y: usize) -> f32 {
+        match self {
+            Dither::None | Dither::FloydSteinberg => 0.5,
+            Dither::Bayer => (BAYER[y % 8][x % 8] as f32 + 0.5) / 64.0,
+            Dither::BlueNoise => blue_noise()[(y % BLUE_NOISE_SIZE) * BLUE_NOISE_SIZE + x % BLUE_NOISE_SIZE],
+        }
+    }
+
+    pub fn quantize<InternalType: InternalColorType, ExternalType: ColorType<InternalType>>(&self, raw: Vec4, x: usize, y: usize) -> ExternalType {
+        let offset = self.threshold(x, y) - 0.5;
+        ExternalType::from_vec4(raw + ExternalType::step() * offset)
+    }
+}
+
+/*
+    Blue noise thresholds generated once with the void-and-cluster method (Ulichney, 1993):
+    ranks are assigned by repeatedly removing the tightest cluster of an initial pattern, and then filling the largest void,
+    where tightness is measured by a toroidal gaussian energy.
+ */
+fn blue_noise() -> &'static [f32] {
+    static THRESHOLDS: OnceLock<Vec<f32>> = OnceLock::new();
+    THRESHOLDS.get_or_init(|| {
+        const N: usize = BLUE_NOISE_SIZE;
+        let mut kernel = vec![0f32; N * N];
+        for dy in 0..N {
+            for dx in 0..N {
+                let x = dx.min(N - dx) as f32;
+                let y = dy.min(N - dy) as f32;
+                kernel[dy * N + dx] = (-(x * x + y * y) / (2.0 * 1.5 * 1.5)).exp();
+            }
+        }
+        let toggle = |energy: &mut [f32], pattern: &mut [bool], p: usize| {
+            pattern[p] = !pattern[p];
+            let sign = if pattern[p] { 1.0 } else { -1.0 };
+            let (px, py) = (p % N, p / N);
+            for y in 0..N {
+                for x in 0..N {
+                    energy[y * N + x] += sign * kernel[((y + N - py) % N) * N + (x + N - px) % N];
+                }
+            }
+        };
+        let extreme = |energy: &[f32], pattern: &[bool], value: bool, tightest: bool| {
+            (0..N * N)
+                .filter(|p| pattern[*p] == value)
+                .max_by(|a, b| {
+                    let order = energy[*a].partial_cmp(&energy[*b]).unwrap();
+                    if tightest { order } else { order.reverse() }
+                })
+                .unwrap()
+        };
+
+        // deterministic initial pattern of about a tenth of the pixels
+        let mut pattern = vec![false; N * N];
+        let mut energy = vec![0f32; N * N];
+        let mut seed = 0x2545f491u32;
+        let mut ones = 0;
+        while ones < N * N / 10 {
+            seed = seed.wrapping_mul(1664525).wrapping_add(1013904223);
+            let p = (seed >> 8) as usize % (N * N);
+            if !pattern[p] {
+                toggle(&mut energy, &mut pattern, p);
+                ones += 1;
+            }
+        }
+        // relax the pattern until removing the tightest cluster creates the largest void
+        for _ in 0..N * N {
+            let cluster = extreme(&energy, &pattern, true, true);
+            toggle(&mut energy, &mut pattern, cluster);
+            let void = extreme(&energy, &pattern, false, false);
+            if void == cluster {
+                toggle(&mut energy, &mut pattern, cluster);
+                break;
+            }
+            toggle(&mut energy, &mut pattern, void);
+        }
+
+        let mut rank = vec![0usize; N * N];
+        let (mut removing, mut removing_energy) = (pattern.clone(), energy.clone());
+        for r in (0..ones).rev() {
+            let cluster = extreme(&removing_energy, &removing, true, true);
+            toggle(&mut removing_energy, &mut removing, cluster);
+            rank[cluster] = r;
+        }
+        for r in ones..N * N {
+            let void = extreme(&energy, &pattern, false, false);
+            toggle(&mut energy, &mut pattern, void);
+            rank[void] = r;
+        }
+        rank.iter().map(|r| (*r as f32 + 0.5) / (N * N) as f32).collect()
+    })
+}
@@ -0,0 +1,9 @@
+// Neighbors a flood fill spreads to
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum Connectivity {
+    // left, right, up and down
+    #[default]
+    Four,
+    // diagonals too
+    Eight
+}
@@ -0,0 +1,70 @@
+use crate::types::colortype::{InternalColorType, ColorType, premultiply, unpremultiply};
+use crate::linalg::Vec4;
+
+/*
+    Space in which blending and filtering happen.
+
+    Stored colors are always sRGB encoded. With `Linear`, colors are decoded to linear light before they are blended, filtered or sampled,
+    and encoded back to sRGB when stored, which avoids dark fringes between contrasting colors. Alpha is never encoded.
+
+    Shader varyings are interpolated in linear light too when the vertex shader points out its colors with `VertexShader::map_colors`;
+    other varyings are interpolated as the vertex shader outputs them.
+ */
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum WorkingSpace {
+    #[default]
+    Gamma,
+    Linear
+}
+
+pub fn srgb_to_linear(c: f32) -> f32 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+pub fn linear_to_srgb(c: f32) -> f32 {
+    if c <= 0.0031308 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+impl WorkingSpace {
+    // straight-alpha sRGB color to working space
+    pub fn decode(&self, raw: Vec4) -> Vec4 {
+        match self {
+            WorkingSpace::Gamma => raw,
+            WorkingSpace::Linear => Vec4::new(srgb_to_linear(raw.x()), srgb_to_linear(raw.y()), srgb_to_linear(raw.z()), raw.w()),
+        }
+    }
+    // straight-alpha working space color to sRGB
+    pub fn encode(&self, raw: Vec4) -> Vec4 {
+        match self {
+            WorkingSpace::Gamma => raw,
+            WorkingSpace::Linear => Vec4::new(linear_to_srgb(raw.x()), linear_to_srgb(raw.y()), linear_to_srgb(raw.z()), raw.w()),
+        }
+    }
+
+    pub fn to_vec4<InternalType: InternalColorType, ExternalType: ColorType<InternalType>>(&self, color: &ExternalType) -> Vec4 {
+        self.decode(color.to_vec4())
+    }
+    pub fn from_vec4<InternalType: InternalColorType, ExternalType: ColorType<InternalType>>(&self, raw: Vec4) -> ExternalType {
+        ExternalType::from_vec4(self.encode(raw))
+    }
+    // premultiplied colors are only exact in gamma space, since premultiplication does not commute with the transfer function
+    pub fn to_premul_vec4<InternalType: InternalColorType, ExternalType: ColorType<InternalType>>(&self, color: &ExternalType) -> Vec4 {
+        match self {
+            WorkingSpace::Gamma => color.to_premul_vec4(),
+            WorkingSpace::Linear => premultiply(self.to_vec4(color)),
+        }
+    }
+    pub fn from_premul_vec4<InternalType: InternalColorType, ExternalType: ColorType<InternalType>>(&self, raw: Vec4) -> ExternalType {
+        match self {
+            WorkingSpace::Gamma => ExternalType::from_premul_vec4(raw),
+            WorkingSpace::Linear => self.from_vec4(unpremultiply(raw)),
+        }
+    }
+}
@@ -0,0 +1,28 @@
+// Pixel counts of evenly spaced bins over [0, 1], of encoded straight colors
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Histogram {
+    // red, green, blue and alpha
+    pub channels: [Vec<usize>; 4],
+    // Rec. 709 luma
+    pub luma: Vec<usize>
+}
+
+impl Histogram {
+    pub fn bins(&self) -> usize {
+        self.luma.len()
+    }
+
+    // lowest value of the bin where the cumulative count reaches `fraction` of the pixels, over counted bins only
+    pub fn percentile(counts: &[usize], fraction: f32) -> f32 {
+        let total: usize = counts.iter().sum();
+        let target = fraction.clamp(0.0, 1.0) * total as f32;
+        let mut seen = 0;
+        for (k, n) in counts.iter().enumerate() {
+            seen += n;
+            if seen > 0 && seen as f32 >= target {
+                return k as f32 / (counts.len() - 1).max(1) as f32;
+            }
+        }
+        1.0
+    }
+}
@@ -0,0 +1,73 @@
+/*
+    Convolution kernel of odd width and height, weights row by row.
+    The center weight applies to the pixel being filtered.
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct Kernel {
+    pub width: usize,
+    pub height: usize,
+    pub weights: Vec<f32>
+}
+
+impl Kernel {
+    pub fn new(width: usize, height: usize, weights: Vec<f32>) -> Self {
+        assert!(width % 2 == 1 && height % 2 == 1, "kernel size must be odd");
+        assert_eq!(weights.len(), width * height);
+        Kernel { width, height, weights }
+    }
+
+    pub fn identity() -> Self {
+        Kernel::new(1, 1, vec![1.0])
+    }
+
+    pub fn sharpen() -> Self {
+        Kernel::new(3, 3, vec![
+             0.0, -1.0,  0.0,
+            -1.0,  5.0, -1.0,
+             0.0, -1.0,  0.0
+        ])
+    }
+
+    // light from the top-left, weights sum to one so flat areas keep their color
+    pub fn emboss() -> Self {
+        Kernel::new(3, 3, vec![
+            -2.0, -1.0, 0.0,
+            -1.0,  1.0, 1.0,
+             0.0,  1.0, 2.0
+        ])
+    }
+
+    pub fn laplacian() -> Self {
+        Kernel::new(3, 3, vec![
+            0.0,  1.0, 0.0,
+            1.0, -4.0, 1.0,
+            0.0,  1.0, 0.0
+        ])
+    }
+
+    // horizontal and vertical gradient kernels of an edge operator
+    pub fn gradient(operator: EdgeOperator) -> (Self, Self) {
+        let side = match operator {
+            EdgeOperator::Sobel => 2.0,
+            EdgeOperator::Prewitt => 1.0,
+        };
+        (
+            Kernel::new(3, 3, vec![
+                -1.0,  0.0, 1.0,
+                -side, 0.0, side,
+                -1.0,  0.0, 1.0
+            ]),
+            Kernel::new(3, 3, vec![
+                -1.0, -side, -1.0,
+                 0.0,  0.0,   0.0,
+                 1.0,  side,  1.0
+            ])
+        )
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum EdgeOperator {
+    Sobel,
+    Prewitt
+}
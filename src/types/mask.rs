@@ -0,0 +1,18 @@
+use crate::linalg::Vec4;
+
+// How the color of a mask canvas is turned into coverage
+#[derive(Copy, Clone)]
+pub enum MaskMode {
+    Alpha,
+    // Rec. 709 luminance, multiplied by alpha
+    Luminance
+}
+
+impl MaskMode {
+    pub fn coverage(&self, color: Vec4) -> f32 {
+        match self {
+            MaskMode::Alpha => color.w(),
+            MaskMode::Luminance => (0.2126 * color.x() + 0.7152 * color.y() + 0.0722 * color.z()) * color.w(),
+        }
+    }
+}
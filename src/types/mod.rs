@@ -1,2 +1,16 @@
 pub mod blend;
-pub mod colortype;
\ No newline at end of file
+pub mod colormatrix;
+pub mod colortype;
+pub mod compare;
+pub mod conversion;
+pub mod curve;
+pub mod dither;
+pub mod fill;
+pub mod gamma;
+pub mod histogram;
+pub mod kernel;
+pub mod mask;
+pub mod morphology;
+pub mod palette;
+pub mod resize;
+pub mod tonemap;
\ No newline at end of file
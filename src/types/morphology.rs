@@ -0,0 +1,7 @@
+// Neighborhood of morphological operations, radii in pixels
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum StructuringElement {
+    // (2 * rx + 1) x (2 * ry + 1) pixels
+    Rect(usize, usize),
+    Disc(usize)
+}
@@ -0,0 +1,7 @@
+// Color quantization used to build palettes
+#[derive(Copy, Clone)]
+pub enum PaletteMethod {
+    MedianCut,
+    // median cut refined by the given number of k-means iterations
+    KMeans(usize)
+}
@@ -0,0 +1,56 @@
+use std::f32::consts::PI;
+
+// Reconstruction kernels for resampling
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ResizeKernel {
+    Box,
+    Bilinear,
+    // Catmull-Rom
+    Bicubic,
+    // Mitchell-Netravali, B = C = 1/3
+    Mitchell,
+    // Lanczos windowed sinc with 3 lobes
+    Lanczos3
+}
+
+impl ResizeKernel {
+    // radius outside which the kernel is zero, in source pixels when upscaling
+    pub fn support(&self) -> f32 {
+        match self {
+            ResizeKernel::Box => 0.5,
+            ResizeKernel::Bilinear => 1.0,
+            ResizeKernel::Bicubic | ResizeKernel::Mitchell => 2.0,
+            ResizeKernel::Lanczos3 => 3.0,
+        }
+    }
+
+    pub fn weight(&self, x: f32) -> f32 {
+        let x = x.abs();
+        match self {
+            ResizeKernel::Box => if x <= 0.5 { 1.0 } else { 0.0 },
+            ResizeKernel::Bilinear => (1.0 - x).max(0.0),
+            ResizeKernel::Bicubic => cubic(x, 0.0, 0.5),
+            ResizeKernel::Mitchell => cubic(x, 1.0 / 3.0, 1.0 / 3.0),
+            ResizeKernel::Lanczos3 => {
+                if x >= 3.0 {
+                    0.0
+                } else if x < f32::EPSILON {
+                    1.0
+                } else {
+                    3.0 * (PI * x).sin() * (PI * x / 3.0).sin() / (PI * PI * x * x)
+                }
+            },
+        }
+    }
+}
+
+// cubic filters of Mitchell and Netravali
+fn cubic(x: f32, b: f32, c: f32) -> f32 {
+    if x < 1.0 {
+        ((12.0 - 9.0 * b - 6.0 * c) * x * x * x + (-18.0 + 12.0 * b + 6.0 * c) * x * x + (6.0 - 2.0 * b)) / 6.0
+    } else if x < 2.0 {
+        ((-b - 6.0 * c) * x * x * x + (6.0 * b + 30.0 * c) * x * x + (-12.0 * b - 48.0 * c) * x + (8.0 * b + 24.0 * c)) / 6.0
+    } else {
+        0.0
+    }
+}
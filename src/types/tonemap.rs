@@ -0,0 +1,35 @@
+use crate::linalg::Vec4;
+
+// Operators mapping high dynamic range colors into [0, 1]. Alpha is clamped and never tone mapped.
+#[derive(Copy, Clone)]
+pub enum ToneMap {
+    Clamp,
+    Reinhard,
+    // Reinhard with the given white point mapped to 1.0
+    ExtendedReinhard(f32),
+    // Narkowicz's fit of the ACES filmic curve
+    Aces
+}
+
+impl ToneMap {
+    /*
+        Map a color, scaled by 2^exposure first.
+     */
+    pub fn map(&self, raw: Vec4, exposure: f32) -> Vec4 {
+        let scale = exposure.exp2();
+        let mut ans = Vec4::new(0.0, 0.0, 0.0, raw.w().clamp(0.0, 1.0));
+        for i in 0..3 {
+            ans.v[i] = self.map_channel((raw.v[i] * scale).max(0.0)).clamp(0.0, 1.0);
+        }
+        ans
+    }
+
+    fn map_channel(&self, x: f32) -> f32 {
+        match self {
+            ToneMap::Clamp => x,
+            ToneMap::Reinhard => x / (1.0 + x),
+            ToneMap::ExtendedReinhard(white) => x * (1.0 + x / (white * white)) / (1.0 + x),
+            ToneMap::Aces => (x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14),
+        }
+    }
+}